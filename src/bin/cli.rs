@@ -1,24 +1,212 @@
 // TÄMÄ ALKU MUUTTUU:
-use bussivahti_pro::models::StopData;
-use bussivahti_pro::{network, settings, ui}; // Tuodaan kirjastosta
+use bussivahti_pro::models::{self, StopId, StopState};
+use bussivahti_pro::{logging, network, plan, schema, settings, source, ui}; // Tuodaan kirjastosta
 // (Poista vanhat "mod models;" rivit jos niitä oli tässä tiedostossa)
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::{collections::HashMap, io, sync::Arc, time::Duration};
+use std::{collections::HashMap, io, path::{Path, PathBuf}, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
-type AppState = Arc<RwLock<HashMap<String, StopData>>>;
+type AppState = Arc<RwLock<HashMap<StopId, StopState>>>;
+
+#[derive(Parser)]
+#[command(name = "bussivahti-cli", about = "Nyssen lähtötaulu terminaalissa")]
+struct Cli {
+    /// Asetustiedosto. Oletuksena $BUSSIVAHTI_CONFIG, ~/.config/bussivahti/Settings.toml tai ./Settings.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Reittiopas: paikannimi, pysäkki tai koordinaatit muodossa "61.4978,23.7603"
+    Plan {
+        from: String,
+        to: String,
+        /// Lähtöaika tänään, esim. 08:30
+        #[arg(long, conflicts_with = "arrive")]
+        at: Option<String>,
+        /// Perillä viimeistään, esim. 09:00
+        #[arg(long)]
+        arrive: Option<String>,
+        /// Sallitut kulkumuodot pilkulla eroteltuna: bus,tram,rail,subway,ferry
+        #[arg(long, value_delimiter = ',')]
+        modes: Vec<String>,
+        /// Montako matkaehdotusta haetaan
+        #[arg(long, default_value_t = 3)]
+        count: usize,
+    },
+    /// Lähellä olevat pysäkit ja niiden linjat
+    Nearby {
+        #[arg(long, allow_hyphen_values = true)]
+        lat: f64,
+        #[arg(long, allow_hyphen_values = true)]
+        lon: f64,
+        /// Hakusäde metreinä
+        #[arg(long, default_value_t = 500)]
+        radius: u32,
+    },
+    /// Asetustiedoston hallinta
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Tarkistaa asetustiedoston ja listaa kaikki ongelmat kerralla
+    Check {
+        /// Tarkistettava tiedosto (oletus sama kuin ohjelman käynnistyessä)
+        path: Option<PathBuf>,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let settings = settings::Settings::new().expect("Virhe: Settings.toml puuttuu tai on viallinen!");
-    let stop_order: Vec<String> = settings.stops.keys().cloned().collect(); 
+    let cli = Cli::parse();
+    // Tarkistus ajetaan ennen latausta, jotta se toimii myös rikkinäisellä tiedostolla
+    if let Some(Command::Config { command: ConfigCommand::Check { path } }) = &cli.command {
+        let path = match settings::find_config(path.as_deref().or(cli.config.as_deref())) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        std::process::exit(run_config_check(&path));
+    }
+
+    let settings = settings::Settings::new(cli.config.as_deref()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    // Alikomennot tulostavat lokin myös konsoliin; TUI:ssa se sotkisi ruudun
+    let log = logging::init(&settings.logging, "bussivahti-cli", cli.command.is_some());
+    for warning in &settings.warnings {
+        tracing::warn!("Asetukset: {}", warning);
+    }
+
+    match cli.command {
+        Some(Command::Plan { from, to, at, arrive, modes, count }) => {
+            run_plan(&settings, &from, &to, at, arrive, &modes, count).await
+        }
+        Some(Command::Nearby { lat, lon, radius }) => run_nearby(&settings, lat, lon, radius).await,
+        Some(Command::Config { .. }) => unreachable!("käsitelty yllä"),
+        None => run_dashboard(settings, log.buffer.clone()).await,
+    }
+}
+
+async fn run_plan(
+    settings: &settings::Settings,
+    from: &str,
+    to: &str,
+    at: Option<String>,
+    arrive: Option<String>,
+    modes: &[String],
+    count: usize,
+) -> Result<()> {
+    let now = models::now();
+    let clock = |text: &str| plan::parse_clock(text, now).ok_or_else(|| anyhow!("Virheellinen kellonaika: {} (muoto HH:MM)", text));
+    let time = match (at, arrive) {
+        (Some(t), _) => plan::TimeConstraint::DepartAt(clock(&t)?),
+        (None, Some(t)) => plan::TimeConstraint::ArriveBy(clock(&t)?),
+        (None, None) => plan::TimeConstraint::Now,
+    };
+    let modes = modes.iter()
+        .map(|m| plan::TransitMode::parse(m).ok_or_else(|| anyhow!("Tuntematon kulkumuoto: {}", m)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let client = network::client(&settings.retry);
+    let request = plan::PlanRequest {
+        from: plan::resolve_place(&client, from, &settings.api_key, &settings.region).await?,
+        to: plan::resolve_place(&client, to, &settings.api_key, &settings.region).await?,
+        time,
+        modes,
+        count,
+    };
+    println!("{} → {}", request.from.name, request.to.name);
+
+    let itineraries = plan::plan(&client, &request, &settings.api_key, &settings.region).await?;
+    if itineraries.is_empty() {
+        println!("Ei reittiehdotuksia.");
+    }
+    for (i, itinerary) in itineraries.iter().enumerate() {
+        println!();
+        println!(
+            "{}) {} – {} ({} min), vaihtoja {}, kävelyä {:.0} m",
+            i + 1,
+            itinerary.start.format("%H:%M"),
+            itinerary.end.format("%H:%M"),
+            itinerary.duration_minutes(),
+            itinerary.transfers,
+            itinerary.walk_distance,
+        );
+        for leg in &itinerary.legs {
+            let rt = if leg.is_realtime { "" } else { "~" };
+            println!("   {}{}  {}", rt, leg.start.format("%H:%M"), leg);
+        }
+    }
+    Ok(())
+}
+
+async fn run_nearby(settings: &settings::Settings, lat: f64, lon: f64, radius: u32) -> Result<()> {
+    let stops = network::stops_by_radius(&network::client(&settings.retry), lat, lon, radius, &settings.api_key, &settings.region).await?;
+    if stops.is_empty() {
+        println!("Ei pysäkkejä {} m säteellä.", radius);
+    }
+    for stop in stops {
+        println!(
+            "{:>5} m  {:<16} {} ({})  linjat: {}",
+            stop.distance,
+            stop.gtfs_id,
+            stop.name,
+            stop.code.as_deref().unwrap_or("-"),
+            stop.lines.join(", "),
+        );
+    }
+    Ok(())
+}
+
+// Palauttaa prosessin paluukoodin: 1 jos virheitä löytyi
+fn run_config_check(path: &Path) -> i32 {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("{}: tiedostoa ei voitu lukea: {}", path.display(), e);
+            return 1;
+        }
+    };
+    let overrides = settings::Overrides::from_env();
+    for var in overrides.describe() {
+        println!("{}: ympäristömuuttuja {}", path.display(), var);
+    }
+    let mut problems = schema::check_provided(&text, &overrides);
+    problems.extend(overrides.problems());
+    if problems.is_empty() {
+        println!("{}: asetukset kunnossa ✔", path.display());
+        return 0;
+    }
+    for problem in &problems {
+        println!("{}: {}", path.display(), problem);
+    }
+    let errors = problems.iter().filter(|p| p.is_error()).count();
+    println!("{} virhettä, {} varoitusta", errors, problems.len() - errors);
+    if errors > 0 { 1 } else { 0 }
+}
+
+async fn run_dashboard(settings: settings::Settings, log: logging::LogBuffer) -> Result<()> {
+    let departure_source = source::from_settings(&settings)?;
+    tracing::info!(stops = settings.stops.len(), region = %settings.region.name, config = ?settings.path, "Käynnistetään lähtötaulu");
 
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
@@ -37,32 +225,45 @@ async fn main() -> Result<()> {
 
     let state_clone = app_state.clone();
     let settings_clone = settings.clone();
+    let control = Arc::new(network::PollControl::default());
+    let poll_control = control.clone();
     tokio::spawn(async move {
+        let mut backoff = network::PollBackoff::new(&settings_clone);
         loop {
-            let new_data = network::fetch_all_stops(&settings_clone).await;
+            let new_data = network::fetch_all_stops(departure_source.as_ref(), &settings_clone.stops).await;
+            let delay = backoff.next_delay(&new_data);
             {
                 let mut w = state_clone.write().await;
-                *w = new_data;
+                let now = models::now();
+                for (id, result) in new_data {
+                    w.entry(id).or_default().apply(result, now);
+                }
             }
-            tokio::time::sleep(Duration::from_secs(settings_clone.update_interval)).await;
+            poll_control.wait(delay).await;
         }
     });
 
+    let mut tui = ui::TuiState::new(&settings);
     let tick_rate = Duration::from_millis(250);
     let mut last_tick = std::time::Instant::now();
 
     loop {
         {
             let data = app_state.read().await;
-            terminal.draw(|f| ui::render(f, &data, &stop_order))?;
+            let log_lines = tui.show_log.then(|| log.lines());
+            terminal.draw(|f| ui::render(f, &data, &settings.stops, log_lines.as_deref(), &mut tui))?;
         }
 
         let timeout = tick_rate.checked_sub(last_tick.elapsed()).unwrap_or_else(|| Duration::from_secs(0));
         if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if let KeyCode::Char('q') = key.code {
-                    break;
+            match tui.handle_event(&event::read()?) {
+                Some(ui::Command::Quit) => break,
+                Some(ui::Command::Refresh) => control.refresh_now(),
+                Some(ui::Command::Pause(paused)) => {
+                    tracing::info!(paused, "Päivitys {}", if paused { "keskeytetty" } else { "jatkuu" });
+                    control.set_paused(paused);
                 }
+                None => {}
             }
         }
 
@@ -1,4 +1,4 @@
-use bussivahti_pro::{models::{StopData, GeoProperties}, network, settings};
+use bussivahti_pro::{registry::{RegistryError, StopRegistry}, logging::{self, LogBuffer}, models::{self, AlertSeverity, NearbyStop, RouteShape, StopId, StopState, GeoProperties}, network, plan::{self, Itinerary}, settings, source::{self, DepartureSource}, vehicles::{self, VehicleLayer, VehicleStore}};
 use eframe::egui;
 use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
 use tokio::runtime::Runtime;
@@ -8,9 +8,18 @@ use walkers::{Map, MapMemory, HttpTiles, Position, sources::OpenStreetMap, Plugi
 struct BussivahtiGui {
     tiles: HttpTiles,
     map_memory: MapMemory,
-    stops: Arc<Mutex<HashMap<String, StopData>>>,
+    stops: Arc<Mutex<HashMap<StopId, StopState>>>,
+    // Seurattavat pysäkit järjestyksessä; muutokset tallentuvat asetustiedostoon
+    registry: StopRegistry,
+    vehicles: VehicleStore,
+    route_shapes: Arc<Mutex<Vec<RouteShape>>>,
+    // Sivupaneelista valittu linja, jonka reitti piirretään kartalle
+    selected_line: Option<String>,
     rt: Runtime,
     settings: settings::Settings,
+    source: Arc<dyn DepartureSource>,
+    // Haut, reittiopas ja lähipysäkit; aikakatkaisu [retry]-asetuksista
+    client: reqwest::Client,
 
     // HAKU & NAVIGOINTI
     search_text: String,
@@ -18,34 +27,95 @@ struct BussivahtiGui {
     is_searching: Arc<Mutex<bool>>,
     map_center_pos: Position,
 
+    // REITTIOPAS
+    plan_from: String,
+    plan_to: String,
+    plan_time: String,
+    plan_arrive_by: bool,
+    plan_results: Arc<Mutex<Result<Vec<Itinerary>, String>>>,
+    is_planning: Arc<Mutex<bool>>,
+    selected_itinerary: usize,
+
+    // PYSÄKKIEN HALLINTA
+    stop_editor: Option<StopEditor>,
+    // Viimeisin rekisterin virhe (esim. tallennus epäonnistui), näytetään sivupaneelissa
+    registry_error: Option<String>,
+
+    // LÄHIPYSÄKIT (hiiren oikea painike kartalla)
+    nearby_origin: Option<Position>,
+    nearby_results: Arc<Mutex<Result<Vec<NearbyStop>, String>>>,
+    is_loading_nearby: Arc<Mutex<bool>>,
+
     // UUSI: UI Skaalaus
     ui_scale: f32,
+
+    // LOKI
+    log: LogBuffer,
+    show_log: bool,
+}
+
+// Linjahaun tulos; None = haku kesken
+type LineList = Arc<Mutex<Option<Result<Vec<String>, String>>>>;
+
+// Pysäkin lisäys- ja muokkausikkuna: oma nimi ja linjasuodatin
+struct StopEditor {
+    id: StopId,
+    // Uusi pysäkki lisätään tallennettaessa, muuten muokataan olemassa olevaa
+    is_new: bool,
+    stop_name: String,
+    name: String,
+    all_lines: bool,
+    selected: Vec<String>,
+    // Pysäkin kautta kulkevat linjat
+    available: LineList,
+}
+
+// Sivupaneelin pysäkkitoiminnot; suoritetaan piirron jälkeen, kun lukot on vapautettu
+enum StopAction {
+    Edit(StopId),
+    Remove(StopId),
+    Move { id: StopId, target: StopId, after: bool },
 }
 
 // --- PLUGIN STRUCT ---
 struct BusMarkerPlugin<'a> {
-    stops: &'a HashMap<String, StopData>,
+    stops: &'a HashMap<StopId, StopState>,
 }
 
 impl<'a> Plugin for BusMarkerPlugin<'a> {
     fn run(self: Box<Self>, ui: &mut egui::Ui, _response: &egui::Response, projector: &Projector) {
         let painter = ui.painter();
+        let now = models::now();
         
-        for stop in self.stops.values() {
+        for state in self.stops.values() {
+            // Ilman yhtään onnistunutta hakua ei ole koordinaatteja, joten merkkiä ei piirretä
+            let Some(stop) = &state.data else { continue };
             let position = Position::from_lon_lat(stop.lon, stop.lat);
             let screen_position = projector.project(position).to_pos2();
 
             // Määritä väri
-            let min_minutes = stop.departures.first().map(|d| d.minutes_left).unwrap_or(99);
+            let min_minutes = stop.next_boardable(now).map(|d| d.minutes_left(now)).unwrap_or(99);
             let color = if min_minutes <= 2 { egui::Color32::RED }
                        else if min_minutes <= 5 { egui::Color32::YELLOW }
                        else { egui::Color32::GREEN };
 
-            // 1. Piirrä pallo
+            // 1. Piirrä pallo (vanhentunut data harmaalla reunuksella)
             painter.circle_filled(screen_position, 10.0, color);
+            if state.is_stale() {
+                painter.circle_stroke(screen_position, 12.0, egui::Stroke::new(3.0, egui::Color32::DARK_GRAY));
+            }
+
+            // Häiriötiedote: varoituskolmio pallon oikeaan yläkulmaan
+            let alerts = stop.active_alerts(now);
+            if let Some(worst) = alerts.first() {
+                let icon_pos = screen_position + egui::vec2(10.0, -10.0);
+                painter.circle_filled(icon_pos, 8.0, severity_color(worst.severity));
+                painter.text(icon_pos, egui::Align2::CENTER_CENTER, "⚠", egui::FontId::proportional(11.0), egui::Color32::BLACK);
+            }
             
             // 2. Piirrä teksti
-            let text = format!("{}\n{} min", stop.stop_name, min_minutes);
+            let stale_mark = if state.is_stale() { "⚠ " } else { "" };
+            let text = format!("{}{}\n{} min", stale_mark, stop.stop_name, min_minutes);
             let text_pos = screen_position + egui::vec2(0.0, 15.0);
             
             painter.text(
@@ -65,6 +135,20 @@ impl<'a> Plugin for BusMarkerPlugin<'a> {
             response.on_hover_ui(|ui| {
                 ui.heading(&stop.stop_name);
                 ui.separator();
+
+                for alert in &alerts {
+                    let lines = if alert.lines.is_empty() { String::new() } else { format!("[{}] ", alert.lines.join(", ")) };
+                    ui.colored_label(severity_color(alert.severity), format!("⚠ {}{}", lines, alert.header));
+                    if !alert.description.is_empty() {
+                        ui.label(egui::RichText::new(&alert.description).small());
+                    }
+                    if let Some(end) = alert.end {
+                        ui.small(format!("Voimassa {} asti", end.format("%d.%m. %H:%M")));
+                    }
+                }
+                if !alerts.is_empty() {
+                    ui.separator();
+                }
                 
                 egui::Grid::new("departures_grid")
                     .striped(true)
@@ -72,67 +156,300 @@ impl<'a> Plugin for BusMarkerPlugin<'a> {
                     .show(ui, |ui| {
                         ui.strong("Linja");
                         ui.strong("Määränpää");
+                        ui.strong("Laituri");
                         ui.strong("Aika");
                         ui.end_row();
 
-                        for dep in &stop.departures {
-                            let time_color = if dep.minutes_left <= 2 { egui::Color32::RED }
-                                            else if dep.minutes_left <= 5 { egui::Color32::YELLOW }
+                        for dep in stop.upcoming(now) {
+                            let minutes_left = dep.minutes_left(now);
+                            let time_color = if minutes_left <= 2 { egui::Color32::RED }
+                                            else if minutes_left <= 5 { egui::Color32::YELLOW }
                                             else { egui::Color32::GREEN };
                             
+                            if dep.is_cancelled() {
+                                ui.label(egui::RichText::new(&dep.line).strong().strikethrough());
+                                ui.label(egui::RichText::new(&dep.headsign).strikethrough());
+                                ui.label(dep.platform.as_deref().unwrap_or("-"));
+                                ui.horizontal(|ui| {
+                                    ui.label(egui::RichText::new(dep.scheduled_time.format("%H:%M").to_string()).strikethrough().weak());
+                                    ui.colored_label(egui::Color32::RED, dep.status.label());
+                                });
+                                ui.end_row();
+                                continue;
+                            }
+
                             ui.strong(&dep.line);
-                            ui.label(&dep.headsign);
-                            ui.colored_label(time_color, format!("{} ({} min)", dep.time_str, dep.minutes_left));
+                            if dep.is_boardable() {
+                                ui.label(&dep.headsign);
+                            } else {
+                                ui.label(egui::RichText::new(format!("{} ({})", dep.headsign, dep.status.label())).weak().italics());
+                            }
+                            ui.label(dep.platform.as_deref().unwrap_or("-"));
+                            ui.horizontal(|ui| {
+                                // Myöhässä/etuajassa: aikataulun aika yliviivattuna ja ennuste perään
+                                if dep.delay_minutes() != 0 {
+                                    ui.label(egui::RichText::new(dep.scheduled_time.format("%H:%M").to_string()).strikethrough().weak());
+                                    ui.colored_label(time_color, format!("{} ({:+}) ({} min)", dep.predicted_time.format("%H:%M"), dep.delay_minutes(), minutes_left));
+                                } else {
+                                    ui.colored_label(time_color, format!("{} ({} min)", dep.predicted_time.format("%H:%M"), minutes_left));
+                                }
+                            });
                             ui.end_row();
                         }
                     });
                     
-                if stop.departures.is_empty() {
+                if stop.upcoming(now).next().is_none() {
                     ui.label("Ei lähtöjä lähiaikoina.");
                 }
                 
                 ui.separator();
                 ui.small(format!("Päivitetty: {}", stop.last_updated.format("%H:%M:%S")));
+                if let Some(err) = &state.error {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(200, 120, 0),
+                        format!("⚠ Vanhentunut: {} ({})", err.kind, err.at.format("%H:%M:%S")),
+                    );
+                }
             });
         }
     }
 }
 
+// --- REITTIVIIVAT ---
+struct RouteShapePlugin<'a> {
+    routes: Vec<&'a RouteShape>,
+}
+
+impl<'a> Plugin for RouteShapePlugin<'a> {
+    fn run(self: Box<Self>, ui: &mut egui::Ui, _response: &egui::Response, projector: &Projector) {
+        let painter = ui.painter();
+
+        for route in self.routes {
+            let color = route.rgb()
+                .map(|(r, g, b)| egui::Color32::from_rgb(r, g, b))
+                .unwrap_or(egui::Color32::from_rgb(28, 87, 207));
+            let stroke = egui::Stroke::new(4.0, color);
+
+            for pattern in &route.patterns {
+                let points: Vec<egui::Pos2> = pattern.geometry.iter()
+                    .map(|c| projector.project(Position::from_lon_lat(c.lon, c.lat)).to_pos2())
+                    .collect();
+                painter.add(egui::Shape::line(points.clone(), stroke));
+                draw_direction_arrows(painter, &points, color);
+
+                for stop in &pattern.stops {
+                    let pos = projector.project(Position::from_lon_lat(stop.lon, stop.lat)).to_pos2();
+                    painter.circle(pos, 3.5, egui::Color32::WHITE, egui::Stroke::new(1.5, color));
+                }
+            }
+        }
+    }
+}
+
+// Nuolenkärki viivan suuntaan noin 80 pikselin välein
+fn draw_direction_arrows(painter: &egui::Painter, points: &[egui::Pos2], color: egui::Color32) {
+    const SPACING: f32 = 80.0;
+    let mut travelled = SPACING / 2.0;
+
+    for segment in points.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let length = a.distance(b);
+        if length < f32::EPSILON {
+            continue;
+        }
+        let dir = (b - a) / length;
+        let side = egui::vec2(-dir.y, dir.x);
+
+        let mut offset = SPACING - travelled;
+        while offset <= length {
+            let tip = a + dir * (offset + 5.0);
+            let base = a + dir * (offset - 5.0);
+            painter.add(egui::Shape::convex_polygon(
+                vec![tip, base + side * 5.0, base - side * 5.0],
+                egui::Color32::WHITE,
+                egui::Stroke::new(1.5, color),
+            ));
+            offset += SPACING;
+        }
+        travelled = (travelled + length) % SPACING;
+    }
+}
+
+// --- REITTIOPAS ---
+struct ItineraryPlugin<'a> {
+    itinerary: &'a Itinerary,
+}
+
+impl<'a> Plugin for ItineraryPlugin<'a> {
+    fn run(self: Box<Self>, ui: &mut egui::Ui, _response: &egui::Response, projector: &Projector) {
+        let painter = ui.painter();
+        let project = |lat: f64, lon: f64| projector.project(Position::from_lon_lat(lon, lat)).to_pos2();
+
+        for leg in &self.itinerary.legs {
+            let points: Vec<egui::Pos2> = leg.geometry.iter().map(|c| project(c.lat, c.lon)).collect();
+            if leg.is_walk() {
+                // Kävely katkoviivalla
+                let stroke = egui::Stroke::new(3.0, egui::Color32::DARK_GRAY);
+                painter.extend(egui::Shape::dashed_line(&points, stroke, 6.0, 4.0));
+            } else {
+                let color = leg.color
+                    .map(|(r, g, b)| egui::Color32::from_rgb(r, g, b))
+                    .unwrap_or(egui::Color32::from_rgb(28, 87, 207));
+                painter.add(egui::Shape::line(points, egui::Stroke::new(5.0, color)));
+            }
+            // Vaihtopaikat
+            let pos = project(leg.from.lat, leg.from.lon);
+            painter.circle(pos, 4.0, egui::Color32::WHITE, egui::Stroke::new(2.0, egui::Color32::BLACK));
+        }
+
+        if let (Some(first), Some(last)) = (self.itinerary.legs.first(), self.itinerary.legs.last()) {
+            painter.circle_filled(project(first.from.lat, first.from.lon), 7.0, egui::Color32::from_rgb(0, 170, 0));
+            painter.circle_filled(project(last.to.lat, last.to.lon), 7.0, egui::Color32::from_rgb(200, 0, 0));
+        }
+    }
+}
+
+// --- KARTAN KLIKKAUS ---
+// Tallentaa oikealla painikkeella klikatun kohdan koordinaatit
+struct MapClickPlugin<'a> {
+    clicked: &'a mut Option<Position>,
+}
+
+impl<'a> Plugin for MapClickPlugin<'a> {
+    fn run(self: Box<Self>, _ui: &mut egui::Ui, response: &egui::Response, projector: &Projector) {
+        if response.secondary_clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                *self.clicked = Some(projector.unproject(pos - response.rect.center()));
+            }
+        }
+    }
+}
+
+// --- AJONEUVOT ---
+struct VehiclePlugin<'a> {
+    layer: &'a VehicleLayer,
+}
+
+impl<'a> Plugin for VehiclePlugin<'a> {
+    fn run(self: Box<Self>, ui: &mut egui::Ui, _response: &egui::Response, projector: &Projector) {
+        let painter = ui.painter();
+
+        for vehicle in self.layer.positions.values() {
+            let center = projector.project(Position::from_lon_lat(vehicle.lon, vehicle.lat)).to_pos2();
+            let color = if vehicle.is_tram() { egui::Color32::from_rgb(218, 33, 40) } else { egui::Color32::from_rgb(0, 90, 170) };
+
+            // Suuntanuoli: kärki ympyrän ulkopuolella kulkusuuntaan
+            if let Some(heading) = vehicle.heading {
+                let rad = heading.to_radians();
+                let dir = egui::vec2(rad.sin(), -rad.cos());
+                let side = egui::vec2(-dir.y, dir.x);
+                let tip = center + dir * 16.0;
+                let base = center + dir * 8.0;
+                painter.add(egui::Shape::convex_polygon(
+                    vec![tip, base + side * 5.0, base - side * 5.0],
+                    color,
+                    egui::Stroke::NONE,
+                ));
+            }
+
+            painter.circle(center, 9.0, color, egui::Stroke::new(1.5, egui::Color32::WHITE));
+            painter.text(center, egui::Align2::CENTER_CENTER, &vehicle.line, egui::FontId::proportional(10.0), egui::Color32::WHITE);
+        }
+    }
+}
+
+fn severity_color(severity: AlertSeverity) -> egui::Color32 {
+    match severity {
+        AlertSeverity::Severe => egui::Color32::RED,
+        AlertSeverity::Warning | AlertSeverity::Unknown => egui::Color32::from_rgb(255, 165, 0),
+        AlertSeverity::Info => egui::Color32::LIGHT_BLUE,
+    }
+}
+
 impl BussivahtiGui {
-    fn new(cc: &eframe::CreationContext<'_>, settings: settings::Settings) -> Self {
+    fn new(cc: &eframe::CreationContext<'_>, settings: settings::Settings, source: Arc<dyn DepartureSource>, log: LogBuffer) -> Self {
         let rt = Runtime::new().expect("Tokio runtime failed");
         let tiles = HttpTiles::new(OpenStreetMap, cc.egui_ctx.clone());
 
-        let stops = Arc::new(Mutex::new(HashMap::new()));
+        let stops: Arc<Mutex<HashMap<StopId, StopState>>> = Arc::new(Mutex::new(HashMap::new()));
+
+        // Pollaus lukee pysäkit rekisteristä joka kierroksella, joten lisäykset ja poistot näkyvät heti
+        let registry = StopRegistry::new(&settings);
+
+        // Ajoneuvojen sijainnit omassa taustatehtävässään; aiheet seuraavat rekisterin linjoja
+        let vehicles: VehicleStore = Arc::new(Mutex::new(VehicleLayer::default()));
+        if settings.vehicles.enabled {
+            rt.spawn(vehicles::run(settings.vehicles.clone(), registry.clone(), vehicles.clone()));
+        }
+
         let stops_clone = stops.clone();
         let settings_clone = settings.clone();
+        let source_clone = source.clone();
+        let registry_clone = registry.clone();
         
         rt.spawn(async move {
+            let mut backoff = network::PollBackoff::new(&settings_clone);
             loop {
-                let new_data = network::fetch_all_stops(&settings_clone).await;
+                let tracked = registry_clone.snapshot();
+                let new_data = network::fetch_all_stops(source_clone.as_ref(), &tracked).await;
+                let delay = backoff.next_delay(&new_data);
                 {
                     let mut lock = stops_clone.lock().unwrap();
-                    for (k, v) in new_data {
-                        lock.insert(k, v);
+                    let now = models::now();
+                    lock.retain(|id, _| registry_clone.contains(id));
+                    for (id, result) in new_data {
+                        lock.entry(id).or_default().apply(result, now);
                     }
                 }
-                tokio::time::sleep(Duration::from_secs(settings_clone.update_interval)).await;
+                tokio::time::sleep(delay).await;
             }
         });
 
+        // Reittien muodot haetaan kerran käynnistyksessä (ei tiedostolähteellä)
+        let client = network::client(&settings.retry);
+        let route_shapes = Arc::new(Mutex::new(Vec::new()));
+        if settings.fixture.is_none() {
+            spawn_route_shape_fetch(&rt, &client, &settings, settings.visible_stops().cloned().collect(), route_shapes.clone());
+        }
+
+        // Kartan aloitusnäkymä alueen asetuksista
+        let mut map_memory = MapMemory::default();
+        let _ = map_memory.set_zoom(settings.region.zoom);
+        let map_center_pos = Position::from_lon_lat(settings.region.center_lon, settings.region.center_lat);
+
         Self {
             tiles,
-            map_memory: MapMemory::default(),
+            map_memory,
             stops,
+            registry,
+            vehicles,
+            route_shapes,
+            selected_line: None,
             rt,
+            client,
             settings,
+            source,
             search_text: String::new(),
             search_results: Arc::new(Mutex::new(Vec::new())),
             is_searching: Arc::new(Mutex::new(false)),
-            map_center_pos: Position::from_lon_lat(23.76, 61.498),
+            plan_from: String::new(),
+            plan_to: String::new(),
+            plan_time: String::new(),
+            plan_arrive_by: false,
+            plan_results: Arc::new(Mutex::new(Ok(Vec::new()))),
+            is_planning: Arc::new(Mutex::new(false)),
+            selected_itinerary: 0,
+            stop_editor: None,
+            registry_error: None,
+            nearby_origin: None,
+            nearby_results: Arc::new(Mutex::new(Ok(Vec::new()))),
+            is_loading_nearby: Arc::new(Mutex::new(false)),
+            map_center_pos,
             
             // Asetetaan oletusskaalaukseksi 1.3
             ui_scale: 1.3,
+            log,
+            show_log: false,
         }
     }
 
@@ -140,38 +457,396 @@ impl BussivahtiGui {
         let text = self.search_text.clone();
         let results_store = self.search_results.clone();
         let loading_flag = self.is_searching.clone();
+        let client = self.client.clone();
         let api_key = self.settings.api_key.clone();
+        let region = self.settings.region.clone();
         
         self.rt.spawn(async move {
             *loading_flag.lock().unwrap() = true;
-            match network::search_stops(&text, &api_key).await {
+            match network::search_stops(&client, &text, &api_key, &region).await {
                 Ok(results) => {
                     *results_store.lock().unwrap() = results;
                 }
                 Err(e) => {
-                    println!("VIRHE HAUSSA: {:?}", e);
+                    tracing::error!(error = %e, text, "Pysäkkihaku epäonnistui");
                 }
             }
             *loading_flag.lock().unwrap() = false;
         });
     }
 
-    fn add_stop_to_tracking(&self, stop_gtfs_id: String) {
+    fn trigger_plan(&mut self) {
+        let from = self.plan_from.clone();
+        let to = self.plan_to.clone();
+        let results_store = self.plan_results.clone();
+        let loading_flag = self.is_planning.clone();
+        let client = self.client.clone();
+        let api_key = self.settings.api_key.clone();
+        let region = self.settings.region.clone();
+        self.selected_itinerary = 0;
+
+        // Tyhjä aika = lähtö nyt
+        let time = if self.plan_time.trim().is_empty() {
+            Some(plan::TimeConstraint::Now)
+        } else {
+            plan::parse_clock(&self.plan_time, models::now()).map(|t| {
+                if self.plan_arrive_by { plan::TimeConstraint::ArriveBy(t) } else { plan::TimeConstraint::DepartAt(t) }
+            })
+        };
+        let Some(time) = time else {
+            *results_store.lock().unwrap() = Err("Kellonaika muodossa HH:MM".to_string());
+            return;
+        };
+
+        self.rt.spawn(async move {
+            *loading_flag.lock().unwrap() = true;
+            let result = async {
+                let request = plan::PlanRequest {
+                    from: plan::resolve_place(&client, &from, &api_key, &region).await?,
+                    to: plan::resolve_place(&client, &to, &api_key, &region).await?,
+                    time,
+                    modes: Vec::new(),
+                    count: 5,
+                };
+                Ok::<_, anyhow::Error>(plan::plan(&client, &request, &api_key, &region).await?)
+            }.await;
+            *results_store.lock().unwrap() = result.map_err(|e| e.to_string());
+            *loading_flag.lock().unwrap() = false;
+        });
+    }
+
+    fn trigger_nearby(&mut self, position: Position) {
+        const RADIUS_METERS: u32 = 400;
+        let results_store = self.nearby_results.clone();
+        let loading_flag = self.is_loading_nearby.clone();
+        let client = self.client.clone();
+        let api_key = self.settings.api_key.clone();
+        let region = self.settings.region.clone();
+        self.nearby_origin = Some(position);
+
+        self.rt.spawn(async move {
+            *loading_flag.lock().unwrap() = true;
+            let result = network::stops_by_radius(&client, position.lat(), position.lon(), RADIUS_METERS, &api_key, &region).await;
+            *results_store.lock().unwrap() = result.map_err(|e| e.to_string());
+            *loading_flag.lock().unwrap() = false;
+        });
+    }
+
+    fn show_nearby_window(&mut self, ctx: &egui::Context) {
+        let Some(origin) = self.nearby_origin else { return };
+        let mut open = true;
+        let mut to_track = None;
+
+        egui::Window::new("Lähipysäkit 📍")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.label(format!("{:.5}, {:.5}", origin.lat(), origin.lon()));
+                if *self.is_loading_nearby.lock().unwrap() {
+                    ui.spinner();
+                }
+                match &*self.nearby_results.lock().unwrap() {
+                    Err(e) => { ui.colored_label(egui::Color32::RED, e); }
+                    Ok(stops) => {
+                        egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                            for stop in stops {
+                                ui.horizontal(|ui| {
+                                    ui.vertical(|ui| {
+                                        ui.strong(format!("{} ({})", stop.name, stop.code.as_deref().unwrap_or("-")));
+                                        ui.label(format!("{} m · {}", stop.distance, stop.lines.join(", ")));
+                                    });
+                                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                        if self.registry.contains(&stop.gtfs_id) {
+                                            ui.label("✔");
+                                        } else if ui.button("➕ Seuraa").clicked() {
+                                            to_track = Some((stop.gtfs_id.clone(), stop.name.clone(), stop.lines.clone()));
+                                        }
+                                    });
+                                });
+                                ui.separator();
+                            }
+                        });
+                    }
+                }
+            });
+
+        if let Some((id, name, lines)) = to_track {
+            self.open_stop_editor(id, name, Some(lines));
+        }
+        if !open {
+            self.nearby_origin = None;
+        }
+    }
+
+    fn show_log_window(&mut self, ctx: &egui::Context) {
+        let lines = self.log.lines();
+        egui::Window::new("Loki 📜")
+            .open(&mut self.show_log)
+            .default_size([600.0, 300.0])
+            .show(ctx, |ui| {
+                if let Some(dir) = logging::log_dir(&self.settings.logging).filter(|_| self.settings.logging.file) {
+                    ui.label(format!("Tiedosto: {}", dir.display()));
+                    ui.separator();
+                }
+                egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                    for line in &lines {
+                        let color = match line.level {
+                            tracing::Level::ERROR => egui::Color32::RED,
+                            tracing::Level::WARN => egui::Color32::YELLOW,
+                            tracing::Level::INFO => egui::Color32::LIGHT_GREEN,
+                            _ => egui::Color32::GRAY,
+                        };
+                        ui.horizontal(|ui| {
+                            ui.monospace(line.at.format("%H:%M:%S").to_string());
+                            ui.colored_label(color, egui::RichText::new(format!("{:<5}", line.level)).monospace());
+                            ui.label(&line.message).on_hover_text(&line.target);
+                        });
+                    }
+                });
+            });
+    }
+
+    fn add_stop_to_tracking(&mut self, config: settings::StopConfig) {
+        // Lisätty pysäkki tulee listan loppuun ja tallentuu asetustiedostoon
+        let result = self.registry.add(config.clone());
+        if self.report(result) {
+            tracing::info!(stop = %config.id, lines = ?config.lines, "Pysäkki lisätty seurantaan");
+            self.fetch_now(config);
+        }
+    }
+
+    // Kirjaa rekisterin virheen. true = muutos on voimassa, vaikka tallennus olisi epäonnistunut.
+    fn report(&mut self, result: Result<(), RegistryError>) -> bool {
+        match result {
+            Ok(()) => {
+                self.registry_error = None;
+                true
+            }
+            Err(e @ (RegistryError::Save { .. } | RegistryError::Parse { .. })) => {
+                tracing::warn!("Pysäkkilistan tallennus epäonnistui: {}", e);
+                self.registry_error = Some(format!("Muutosta ei tallennettu: {}", e));
+                true
+            }
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.registry_error = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    // Haetaan heti, ei vasta seuraavalla pollauskierroksella
+    fn fetch_now(&self, config: settings::StopConfig) {
         let stops_store = self.stops.clone();
-        let mut temp_settings = self.settings.clone();
-        
-        let mut single_stop_map = HashMap::new();
-        single_stop_map.insert(stop_gtfs_id.clone(), vec!["ALL".to_string()]);
-        temp_settings.stops = single_stop_map;
+        let source = self.source.clone();
+        let stops = vec![config];
+        if self.settings.fixture.is_none() {
+            spawn_route_shape_fetch(&self.rt, &self.client, &self.settings, stops.clone(), self.route_shapes.clone());
+        }
 
         self.rt.spawn(async move {
-            let new_data = network::fetch_all_stops(&temp_settings).await;
-            if let Some(data) = new_data.get(&stop_gtfs_id) {
-                let mut lock = stops_store.lock().unwrap();
-                lock.insert(stop_gtfs_id, data.clone());
+            let new_data = network::fetch_all_stops(source.as_ref(), &stops).await;
+            let mut lock = stops_store.lock().unwrap();
+            let now = models::now();
+            for (id, result) in new_data {
+                lock.entry(id).or_default().apply(result, now);
             }
         });
     }
+
+    fn apply_stop_action(&mut self, action: StopAction) {
+        match action {
+            StopAction::Edit(id) => {
+                let name = self.stops.lock().unwrap().get(&id)
+                    .and_then(|s| s.data.as_ref())
+                    .map(|d| d.stop_name.clone())
+                    .unwrap_or_else(|| id.to_string());
+                self.open_stop_editor(id, name, None);
+            }
+            StopAction::Remove(id) => {
+                let result = self.registry.remove(&id);
+                if self.report(result) {
+                    tracing::info!(stop = %id, "Pysäkki poistettu seurannasta");
+                    self.stops.lock().unwrap().remove(&id);
+                    if self.stop_editor.as_ref().is_some_and(|e| e.id == id) {
+                        self.stop_editor = None;
+                    }
+                }
+            }
+            StopAction::Move { id, target, after } => {
+                let result = self.registry.move_beside(&id, &target, after);
+                self.report(result);
+            }
+        }
+    }
+
+    // Lisäys- tai muokkausikkuna. Linjat haetaan rajapinnasta, ellei niitä tunneta jo (lähipysäkit).
+    fn open_stop_editor(&mut self, id: StopId, stop_name: String, known_lines: Option<Vec<String>>) {
+        let existing = self.registry.get(&id);
+        let (name, all_lines, selected) = match &existing {
+            Some(config) => (
+                config.name.clone().unwrap_or_default(),
+                config.lines.iter().any(|l| l == "ALL"),
+                config.lines.iter().filter(|l| *l != "ALL").cloned().collect(),
+            ),
+            None => (String::new(), true, Vec::new()),
+        };
+
+        let available = Arc::new(Mutex::new(known_lines.map(Ok)));
+        if available.lock().unwrap().is_none() {
+            if self.settings.fixture.is_some() {
+                // Tiedostolähteellä ei ole linjahakua: tarjotaan näkyvien lähtöjen linjat
+                let lines = self.stops.lock().unwrap().get(&id)
+                    .map(departure_lines)
+                    .unwrap_or_default();
+                *available.lock().unwrap() = Some(Ok(lines));
+            } else {
+                let store = available.clone();
+                let client = self.client.clone();
+                let api_key = self.settings.api_key.clone();
+                let region = self.settings.region.clone();
+                let stop_id = id.clone();
+                self.rt.spawn(async move {
+                    let result = network::stop_lines(&client, &stop_id, &api_key, &region).await.map_err(|e| e.to_string());
+                    *store.lock().unwrap() = Some(result);
+                });
+            }
+        }
+
+        self.stop_editor = Some(StopEditor {
+            id,
+            is_new: existing.is_none(),
+            stop_name,
+            name,
+            all_lines,
+            selected,
+            available,
+        });
+    }
+
+    fn show_stop_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.stop_editor else { return };
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+
+        egui::Window::new(if editor.is_new { "Lisää pysäkki" } else { "Muokkaa pysäkkiä" })
+            .id(egui::Id::new("stop_editor"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.strong(format!("{} ({})", editor.stop_name, editor.id));
+                ui.horizontal(|ui| {
+                    ui.label("Oma nimi:");
+                    ui.add(egui::TextEdit::singleline(&mut editor.name).hint_text(&editor.stop_name));
+                });
+                ui.separator();
+
+                ui.checkbox(&mut editor.all_lines, "Kaikki linjat");
+                ui.add_enabled_ui(!editor.all_lines, |ui| {
+                    match &*editor.available.lock().unwrap() {
+                        None => {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label("Haetaan linjoja...");
+                            });
+                        }
+                        Some(Err(e)) => {
+                            ui.colored_label(egui::Color32::RED, format!("Linjoja ei saatu: {}", e));
+                        }
+                        Some(Ok(lines)) => {
+                            // Asetuksissa olevat linjat näytetään, vaikka rajapinta ei niitä enää tuntisi
+                            let mut choices = lines.clone();
+                            for line in &editor.selected {
+                                if !choices.contains(line) {
+                                    choices.push(line.clone());
+                                }
+                            }
+                            if choices.is_empty() {
+                                ui.label("Pysäkiltä ei löytynyt linjoja.");
+                            }
+                            ui.horizontal_wrapped(|ui| {
+                                for line in choices {
+                                    let mut on = editor.selected.contains(&line);
+                                    if ui.checkbox(&mut on, &line).changed() {
+                                        if on {
+                                            editor.selected.push(line);
+                                        } else {
+                                            editor.selected.retain(|l| *l != line);
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                    }
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let valid = editor.all_lines || !editor.selected.is_empty();
+                    let label = if editor.is_new { "➕ Lisää" } else { "💾 Tallenna" };
+                    if ui.add_enabled(valid, egui::Button::new(label)).on_disabled_hover_text("Valitse vähintään yksi linja").clicked() {
+                        save = true;
+                    }
+                    if ui.button("Peruuta").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if !open || cancel {
+            self.stop_editor = None;
+        }
+        if !save {
+            return;
+        }
+        let Some(editor) = self.stop_editor.take() else { return };
+        let lines = if editor.all_lines {
+            vec!["ALL".to_string()]
+        } else {
+            let mut lines = editor.selected;
+            lines.sort_by(|a, b| network::natural_line_order(a, b));
+            lines
+        };
+        let name = Some(editor.name.trim().to_string()).filter(|n| !n.is_empty());
+
+        if editor.is_new {
+            let mut config = settings::StopConfig::new(editor.id, lines);
+            config.name = name;
+            self.add_stop_to_tracking(config);
+        } else {
+            let result = self.registry.update(&editor.id, |config| {
+                config.lines = lines;
+                config.name = name;
+            });
+            if self.report(result) {
+                if let Some(config) = self.registry.get(&editor.id) {
+                    self.fetch_now(config);
+                }
+            }
+        }
+    }
+}
+
+// Lisää haetut reitit jaettuun listaan (sama reitti vain kerran)
+fn spawn_route_shape_fetch(rt: &Runtime, client: &reqwest::Client, settings: &settings::Settings, stops: Vec<settings::StopConfig>, store: Arc<Mutex<Vec<RouteShape>>>) {
+    let client = client.clone();
+    let api_key = settings.api_key.clone();
+    let region = settings.region.clone();
+    rt.spawn(async move {
+        match network::fetch_route_shapes(&client, &api_key, &region, &stops).await {
+            Ok(routes) => {
+                let mut lock = store.lock().unwrap();
+                for route in routes {
+                    if !lock.iter().any(|r| r.gtfs_id == route.gtfs_id) {
+                        lock.push(route);
+                    }
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, stops = stops.len(), "Reittien muotojen haku epäonnistui"),
+        }
+    });
 }
 
 impl eframe::App for BussivahtiGui {
@@ -180,6 +855,9 @@ impl eframe::App for BussivahtiGui {
         // KÄYTÄ SKAALAUSTA
         ctx.set_pixels_per_point(self.ui_scale);
 
+        // Pysäkkilistan muutokset tehdään vasta paneelin piirron jälkeen
+        let mut stop_action = None;
+
         // --- SIVUPANEELI ---
         egui::SidePanel::left("menu_panel")
             .resizable(true)
@@ -192,6 +870,14 @@ impl eframe::App for BussivahtiGui {
                     ui.label("Koko:");
                     ui.add(egui::Slider::new(&mut self.ui_scale, 0.8..=2.5).text("x"));
                 });
+                ui.toggle_value(&mut self.show_log, "📜 Loki");
+                if self.settings.vehicles.enabled {
+                    let layer = self.vehicles.lock().unwrap();
+                    match &layer.error {
+                        Some(err) => { ui.colored_label(egui::Color32::RED, format!("🚍 Ajoneuvosyöte: {}", err)); }
+                        None => { ui.label(format!("🚍 Ajoneuvoja kartalla: {}", layer.positions.len())); }
+                    }
+                }
                 ui.separator();
 
                 ui.heading("Haku 🔍");
@@ -210,6 +896,56 @@ impl eframe::App for BussivahtiGui {
 
                 ui.separator();
 
+                egui::CollapsingHeader::new("Reittiopas 🧭").show(ui, |ui| {
+                    egui::Grid::new("plan_form").num_columns(2).show(ui, |ui| {
+                        ui.label("Mistä:");
+                        ui.text_edit_singleline(&mut self.plan_from);
+                        ui.end_row();
+                        ui.label("Minne:");
+                        ui.text_edit_singleline(&mut self.plan_to);
+                        ui.end_row();
+                        ui.label("Klo:");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.plan_time).hint_text("nyt").desired_width(50.0));
+                            ui.checkbox(&mut self.plan_arrive_by, "Perillä");
+                        });
+                        ui.end_row();
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Hae reitti").clicked() {
+                            self.trigger_plan();
+                        }
+                        if *self.is_planning.lock().unwrap() {
+                            ui.spinner();
+                        }
+                    });
+
+                    match &*self.plan_results.lock().unwrap() {
+                        Err(e) => { ui.colored_label(egui::Color32::RED, e); }
+                        Ok(itineraries) => {
+                            for (i, itinerary) in itineraries.iter().enumerate() {
+                                let summary = format!(
+                                    "{} – {} ({} min, vaihtoja {})",
+                                    itinerary.start.format("%H:%M"),
+                                    itinerary.end.format("%H:%M"),
+                                    itinerary.duration_minutes(),
+                                    itinerary.transfers,
+                                );
+                                if ui.selectable_label(self.selected_itinerary == i, summary).clicked() {
+                                    self.selected_itinerary = i;
+                                }
+                                if self.selected_itinerary == i {
+                                    for leg in &itinerary.legs {
+                                        ui.label(format!("   {}  {}", leg.start.format("%H:%M"), leg));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                });
+
+                ui.separator();
+
                 // --- SCROLL ALUE ---
                 egui::ScrollArea::vertical()
                     .auto_shrink([false, false])
@@ -234,18 +970,15 @@ impl eframe::App for BussivahtiGui {
                                         });
 
                                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                            if let Some(raw_id) = &result.gtfs_id {
-                                                if ui.button("➕").clicked() {
-                                                    // SIIVOTAAN ID
-                                                    let clean_id = raw_id
-                                                        .replace("GTFS:", "")
-                                                        .split('#').next()
-                                                        .unwrap_or("")
-                                                        .to_string();
-                                                    self.add_stop_to_tracking(clean_id);
+                                            match result.stop_id(&self.settings.region.feed_id) {
+                                                Ok(stop_id) => {
+                                                    if ui.button("➕").on_hover_text(stop_id.as_str()).clicked() {
+                                                        self.open_stop_editor(stop_id, result.name.clone(), None);
+                                                    }
+                                                }
+                                                Err(e) => {
+                                                    ui.colored_label(egui::Color32::RED, "🚫 Ei ID").on_hover_text(e.to_string());
                                                 }
-                                            } else {
-                                                ui.colored_label(egui::Color32::RED, "🚫 Ei ID");
                                             }
                                         });
                                     });
@@ -256,18 +989,109 @@ impl eframe::App for BussivahtiGui {
 
                         // SEURATTAVAT
                         ui.heading("Seurannassa:");
+                        if let Some(err) = &self.registry_error {
+                            ui.colored_label(egui::Color32::RED, format!("⚠ {}", err));
+                        }
                         let tracked = self.stops.lock().unwrap();
-                        
-                        for stop in tracked.values() {
-                            if ui.button(format!("📍 {}", stop.stop_name)).clicked() {
-                                self.map_center_pos = Position::from_lon_lat(stop.lon, stop.lat);
-                                self.map_memory = MapMemory::default();
+                        let empty = StopState::default();
+                        let visible = self.registry.visible();
+
+                        // Asetusten järjestyksessä; pienennetyt pysäkit alkavat suljettuina.
+                        // ☰-kahvasta raahaamalla järjestys muuttuu, oikealla painikkeella muokataan.
+                        for (i, config) in visible.iter().enumerate() {
+                            let id = &config.id;
+                            let state = tracked.get(id).unwrap_or(&empty);
+                            let label = match (&config.name, &state.data) {
+                                (Some(name), _) => name.clone(),
+                                (None, Some(stop)) => stop.stop_name.clone(),
+                                (None, None) => id.to_string(),
+                            };
+
+                            let item = ui.scope(|ui| {
+                                let state_id = ui.make_persistent_id(("tracked_stop", id.as_str()));
+                                egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), state_id, !config.collapsed)
+                                    .show_header(ui, |ui| {
+                                        ui.dnd_drag_source(state_id.with("drag"), id.clone(), |ui| {
+                                            ui.label("☰");
+                                        })
+                                        .response
+                                        .on_hover_text("Vedä siirtääksesi");
+
+                                        let name = ui.add(egui::Button::new(format!("📍 {}", label)).frame(false))
+                                            .on_hover_text("Näytä kartalla · oikea painike: muokkaa");
+                                        if name.clicked() {
+                                            if let Some(stop) = &state.data {
+                                                self.map_center_pos = Position::from_lon_lat(stop.lon, stop.lat);
+                                                self.map_memory = MapMemory::default();
+                                            }
+                                        }
+                                        name.context_menu(|ui| {
+                                            if ui.button("✏ Nimi ja linjat…").clicked() {
+                                                stop_action = Some(StopAction::Edit(id.clone()));
+                                                ui.close_menu();
+                                            }
+                                            if i > 0 && ui.button("⬆ Siirrä ylös").clicked() {
+                                                stop_action = Some(StopAction::Move { id: id.clone(), target: visible[i - 1].id.clone(), after: false });
+                                                ui.close_menu();
+                                            }
+                                            if i + 1 < visible.len() && ui.button("⬇ Siirrä alas").clicked() {
+                                                stop_action = Some(StopAction::Move { id: id.clone(), target: visible[i + 1].id.clone(), after: true });
+                                                ui.close_menu();
+                                            }
+                                            ui.separator();
+                                            if ui.button("🗑 Poista seurannasta").clicked() {
+                                                stop_action = Some(StopAction::Remove(id.clone()));
+                                                ui.close_menu();
+                                            }
+                                        });
+                                    })
+                                    .body(|ui| {
+                                        if config.walk_minutes > 0 {
+                                            ui.label(format!("🚶 {} min", config.walk_minutes));
+                                        }
+                                        if let Some(err) = &state.error {
+                                            ui.colored_label(egui::Color32::RED, format!("⚠ {} ({})", err.kind, err.at.format("%H:%M:%S")))
+                                                .on_hover_text(&err.message);
+                                        }
+
+                                        // Linjat: klikkaus näyttää reitin kartalla, toinen klikkaus piilottaa
+                                        let lines = tracked_lines_for(config, state);
+                                        if !lines.is_empty() {
+                                            ui.horizontal_wrapped(|ui| {
+                                                for line in lines {
+                                                    let selected = self.selected_line.as_deref() == Some(line.as_str());
+                                                    if ui.selectable_label(selected, &line).on_hover_text("Näytä reitti").clicked() {
+                                                        self.selected_line = if selected { None } else { Some(line) };
+                                                    }
+                                                }
+                                            });
+                                        }
+                                    });
+                            }).response;
+
+                            // Pudotuskohta: viiva näyttää, tuleeko pysäkki tämän ylä- vai alapuolelle
+                            let pointer = ui.input(|input| input.pointer.interact_pos());
+                            if let (Some(dragged), Some(pointer)) = (item.dnd_hover_payload::<StopId>(), pointer) {
+                                if *dragged != *id {
+                                    let after = pointer.y > item.rect.center().y;
+                                    let y = if after { item.rect.bottom() } else { item.rect.top() };
+                                    ui.painter().hline(item.rect.x_range(), y, egui::Stroke::new(2.0, ui.visuals().selection.bg_fill));
+                                    if item.dnd_release_payload::<StopId>().is_some() {
+                                        stop_action = Some(StopAction::Move { id: (*dragged).clone(), target: id.clone(), after });
+                                    }
+                                }
                             }
                         }
                     });
             });
 
+        if let Some(action) = stop_action {
+            self.apply_stop_action(action);
+        }
+        self.show_stop_editor(ctx);
+
         // --- KARTTAPANEELI ---
+        let mut clicked = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             let stops_data = {
                 let lock = self.stops.lock().unwrap();
@@ -280,16 +1104,66 @@ impl eframe::App for BussivahtiGui {
                 self.map_center_pos
             );
 
+            let vehicle_layer = self.vehicles.lock().unwrap();
+            let route_shapes = self.route_shapes.lock().unwrap();
+
+            let shapes = RouteShapePlugin {
+                routes: route_shapes.iter()
+                    .filter(|r| self.selected_line.as_deref() == Some(r.short_name.as_str()))
+                    .collect(),
+            };
+            let plan_results = self.plan_results.lock().unwrap();
+            let itinerary = plan_results.as_ref().ok().and_then(|r| r.get(self.selected_itinerary));
+
             let markers = BusMarkerPlugin { stops: &stops_data };
-            ui.add(map.with_plugin(markers));
+            let vehicle_markers = VehiclePlugin { layer: &vehicle_layer };
+            let mut map = map.with_plugin(shapes);
+            if let Some(itinerary) = itinerary {
+                map = map.with_plugin(ItineraryPlugin { itinerary });
+            }
+            ui.add(map.with_plugin(vehicle_markers).with_plugin(markers).with_plugin(MapClickPlugin { clicked: &mut clicked }));
         });
 
+        if let Some(position) = clicked {
+            self.trigger_nearby(position);
+        }
+        self.show_nearby_window(ctx);
+        self.show_log_window(ctx);
+
         ctx.request_repaint_after(Duration::from_secs(1));
     }
 }
 
+// Pysäkin linjat: asetusten lista, tai "ALL"-pysäkeillä näkyvien lähtöjen linjat
+fn tracked_lines_for(config: &settings::StopConfig, state: &StopState) -> Vec<String> {
+    if !config.lines.iter().any(|l| l == "ALL") {
+        return config.lines.clone();
+    }
+    departure_lines(state)
+}
+
+// Näkyvien lähtöjen linjat ensiesiintymisjärjestyksessä
+fn departure_lines(state: &StopState) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for dep in state.data.iter().flat_map(|d| &d.departures) {
+        if !lines.contains(&dep.line) {
+            lines.push(dep.line.clone());
+        }
+    }
+    lines
+}
+
 fn main() -> eframe::Result {
-    let settings = bussivahti_pro::settings::Settings::new().expect("Config error");
+    let settings = settings::Settings::new(settings::config_arg().as_deref()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let log = logging::init(&settings.logging, "bussivahti-gui", true);
+    for warning in &settings.warnings {
+        tracing::warn!("Asetukset: {}", warning);
+    }
+    let source = source::from_settings(&settings).expect("Lähteen alustus epäonnistui");
+    tracing::info!(stops = settings.stops.len(), region = %settings.region.name, config = ?settings.path, "Käynnistetään karttanäkymä");
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1100.0, 700.0]),
         ..Default::default()
@@ -297,6 +1171,6 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Bussivahti Map",
         options,
-        Box::new(|cc| Ok(Box::new(BussivahtiGui::new(cc, settings)))),
+        Box::new(|cc| Ok(Box::new(BussivahtiGui::new(cc, settings, source, log.buffer.clone())))),
     )
 }
\ No newline at end of file
@@ -0,0 +1,262 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+// --- TOIMINNOT ---
+
+// Lähtötaulun toiminnot, joihin [keys]-taulussa sidotaan näppäimet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    NextStop,
+    PrevStop,
+    NextPage,
+    PrevPage,
+    Open,
+    Back,
+    Refresh,
+    Pause,
+    Help,
+    Log,
+    Quit,
+}
+
+impl Action {
+    // Ohjeikkunan järjestys
+    pub const ALL: [Action; 13] = [
+        Action::Up,
+        Action::Down,
+        Action::NextStop,
+        Action::PrevStop,
+        Action::NextPage,
+        Action::PrevPage,
+        Action::Open,
+        Action::Back,
+        Action::Refresh,
+        Action::Pause,
+        Action::Help,
+        Action::Log,
+        Action::Quit,
+    ];
+
+    // Avain [keys]-taulussa
+    pub fn key(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::NextStop => "next_stop",
+            Action::PrevStop => "prev_stop",
+            Action::NextPage => "next_page",
+            Action::PrevPage => "prev_page",
+            Action::Open => "open",
+            Action::Back => "back",
+            Action::Refresh => "refresh",
+            Action::Pause => "pause",
+            Action::Help => "help",
+            Action::Log => "log",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Up => "Edellinen pysäkki / lähtö",
+            Action::Down => "Seuraava pysäkki / lähtö",
+            Action::NextStop => "Seuraava pysäkki",
+            Action::PrevStop => "Edellinen pysäkki",
+            Action::NextPage => "Seuraava sivu",
+            Action::PrevPage => "Edellinen sivu",
+            Action::Open => "Avaa pysäkin tiedot",
+            Action::Back => "Takaisin yleisnäkymään",
+            Action::Refresh => "Päivitä heti",
+            Action::Pause => "Keskeytä / jatka päivitystä",
+            Action::Help => "Näytä / piilota ohje",
+            Action::Log => "Näytä / piilota loki",
+            Action::Quit => "Lopeta",
+        }
+    }
+}
+
+// --- NÄPPÄIN ---
+
+// Yksi näppäin asetuksista, esim. "q", "Enter", "Ctrl+r", "F5"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn new(code: KeyCode) -> Self {
+        Key { code, modifiers: KeyModifiers::NONE }
+    }
+
+    // Shift ohitetaan, ellei sitä ole pyydetty: "?" tulee monilla päätteillä Shiftin kanssa
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        let mut modifiers = event.modifiers;
+        if !self.modifiers.contains(KeyModifiers::SHIFT) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        self.code == event.code && self.modifiers == modifiers
+    }
+}
+
+// Nimetyt näppäimet; kirjainkoolla ei väliä
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Backspace", KeyCode::Backspace),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Space", KeyCode::Char(' ')),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+];
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // "Ctrl+Shift+x": muuntimet ensin, näppäin viimeisenä. Pelkkä "+" on itse näppäin.
+        let (prefix, name) = match s.rsplit_once('+') {
+            Some((prefix, "")) => (prefix.strip_suffix('+').unwrap_or(prefix), "+"),
+            Some((prefix, name)) => (prefix, name),
+            None => ("", s),
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for part in prefix.split('+').filter(|p| !p.is_empty()) {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("tuntematon muunnin \"{}\" näppäimessä \"{}\"", part, s)),
+            };
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match NAMED_KEYS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+                Some((_, code)) => *code,
+                None => match name.strip_prefix(['F', 'f']).and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("tuntematon näppäin \"{}\"", s)),
+                },
+            },
+        };
+        Ok(Key { code, modifiers })
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "Ctrl+"), (KeyModifiers::ALT, "Alt+"), (KeyModifiers::SHIFT, "Shift+")] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => match NAMED_KEYS.iter().find(|(_, c)| *c == code) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+// --- SIDONNAT ---
+
+// [keys]-taulu: jokaiselle toiminnolle lista näppäimiä. Puuttuva avain = oletukset.
+//   [keys]
+//   refresh = ["r", "F5"]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub up: Vec<Key>,
+    pub down: Vec<Key>,
+    pub next_stop: Vec<Key>,
+    pub prev_stop: Vec<Key>,
+    pub next_page: Vec<Key>,
+    pub prev_page: Vec<Key>,
+    pub open: Vec<Key>,
+    pub back: Vec<Key>,
+    pub refresh: Vec<Key>,
+    pub pause: Vec<Key>,
+    pub help: Vec<Key>,
+    pub log: Vec<Key>,
+    pub quit: Vec<Key>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let ch = |c| Key::new(KeyCode::Char(c));
+        Self {
+            up: vec![Key::new(KeyCode::Up), ch('k')],
+            down: vec![Key::new(KeyCode::Down), ch('j')],
+            next_stop: vec![Key::new(KeyCode::Tab)],
+            prev_stop: vec![Key::new(KeyCode::BackTab)],
+            next_page: vec![Key::new(KeyCode::PageDown), Key::new(KeyCode::Right)],
+            prev_page: vec![Key::new(KeyCode::PageUp), Key::new(KeyCode::Left)],
+            open: vec![Key::new(KeyCode::Enter)],
+            back: vec![Key::new(KeyCode::Esc), Key::new(KeyCode::Backspace)],
+            refresh: vec![ch('r')],
+            pause: vec![ch('p')],
+            help: vec![ch('?')],
+            log: vec![ch('l')],
+            quit: vec![ch('q'), Key { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL }],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> &[Key] {
+        match action {
+            Action::Up => &self.up,
+            Action::Down => &self.down,
+            Action::NextStop => &self.next_stop,
+            Action::PrevStop => &self.prev_stop,
+            Action::NextPage => &self.next_page,
+            Action::PrevPage => &self.prev_page,
+            Action::Open => &self.open,
+            Action::Back => &self.back,
+            Action::Refresh => &self.refresh,
+            Action::Pause => &self.pause,
+            Action::Help => &self.help,
+            Action::Log => &self.log,
+            Action::Quit => &self.quit,
+        }
+    }
+
+    // Ensimmäinen toiminto, johon näppäin on sidottu
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| self.keys(*a).iter().any(|k| k.matches(event)))
+    }
+
+    // Ohjeikkunaa ja alapalkkia varten, esim. "r / F5"
+    pub fn describe(&self, action: Action) -> String {
+        self.keys(action).iter().map(Key::to_string).collect::<Vec<_>>().join(" / ")
+    }
+}
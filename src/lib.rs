@@ -1,5 +1,12 @@
 // Tämä tiedosto vain jakaa moduulit binääreille
+pub mod keys;
+pub mod logging;
 pub mod models;
 pub mod network;
+pub mod plan;
+pub mod registry;
+pub mod schema;
 pub mod settings;
+pub mod source;
+pub mod vehicles;
 pub mod ui; // Vain CLI käyttää tätä, mutta olkoon täällä
\ No newline at end of file
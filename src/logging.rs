@@ -0,0 +1,167 @@
+use crate::models::now;
+use crate::settings::LogSettings;
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as tfmt, EnvFilter, Layer};
+
+// Täydet API-pyynnöt ja -vastaukset kirjataan tälle kohteelle. Se on pois päältä
+// kaikilla tasoilla (myös RUST_LOG=trace), ellei [logging] dump_api = true tai
+// RUST_LOG nimeä kohdetta itse, esim. RUST_LOG=info,api_dump=trace.
+pub const API_DUMP: &str = "api_dump";
+
+// Montako viimeisintä riviä sovelluksen lokinäkymä muistaa
+const BUFFER_LINES: usize = 500;
+// Montako päivän lokitiedostoa säilytetään
+const MAX_LOG_FILES: usize = 7;
+
+// --- LOKINÄKYMÄ (TUI / GUI) ---
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub at: DateTime<Tz>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<LogLine>>>,
+}
+
+impl LogBuffer {
+    pub fn lines(&self) -> Vec<LogLine> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn push(&self, line: LogLine) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= BUFFER_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+struct BufferLayer {
+    buffer: LogBuffer,
+}
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        // Vastausdumpit ovat liian suuria ruudulle, ne menevät vain tiedostoon
+        if meta.target() == API_DUMP {
+            return;
+        }
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.buffer.push(LogLine {
+            at: now(),
+            level: *meta.level(),
+            target: meta.target().to_string(),
+            message: visitor.0,
+        });
+    }
+}
+
+// "viesti avain=arvo avain=arvo"
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0.push_str(value);
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+// --- ALUSTUS ---
+
+// Pidä tallessa main-funktion loppuun asti: pudottaminen tyhjentää tiedostopuskurin
+pub struct LogHandle {
+    pub buffer: LogBuffer,
+    _guard: Option<WorkerGuard>,
+}
+
+// Lokikansio: asetuksista tai käyttäjän datakansiosta, esim. ~/.local/share/bussivahti/logs
+pub fn log_dir(settings: &LogSettings) -> Option<PathBuf> {
+    match &settings.dir {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => dirs::data_dir().map(|d| d.join("bussivahti").join("logs")),
+    }
+}
+
+// Lokisuodatin: RUST_LOG (jos kelvollinen) tai asetusten taso. API-dumpit vain
+// pyydettäessä; hyper hiljennetään vain, jos käyttäjä ei ole antanut omaa suodatinta.
+pub fn env_filter(settings: &LogSettings, rust_log: Option<&str>) -> EnvFilter {
+    let directive = |d: String| d.parse().expect("virheellinen lokisuodatin");
+    let user = rust_log.filter(|s| !s.trim().is_empty()).and_then(|s| EnvFilter::try_new(s).ok().map(|f| (s, f)));
+
+    let (filter, dump_named) = match user {
+        Some((text, filter)) => (filter, names_target(text, API_DUMP)),
+        None => (EnvFilter::new(&settings.level).add_directive(directive("hyper=info".to_string())), false),
+    };
+    match (settings.dump_api, dump_named) {
+        (true, _) => filter.add_directive(directive(format!("{}=trace", API_DUMP))),
+        (false, true) => filter,
+        (false, false) => filter.add_directive(directive(format!("{}=off", API_DUMP))),
+    }
+}
+
+// "info,api_dump=trace" -> true; pelkkä taso tai muut kohteet -> false
+fn names_target(filter: &str, target: &str) -> bool {
+    filter.split(',').any(|d| d.split(['=', '[']).next().is_some_and(|t| t.trim() == target))
+}
+
+// `stderr` = tulostetaanko myös konsoliin. TUI:ssa ei, koska raw mode sotkisi ruudun.
+pub fn init(settings: &LogSettings, app: &str, stderr: bool) -> LogHandle {
+    let filter = env_filter(settings, std::env::var(EnvFilter::DEFAULT_ENV).ok().as_deref());
+
+    let buffer = LogBuffer::default();
+    let mut guard = None;
+    let file_layer = settings.file.then(|| log_dir(settings)).flatten().and_then(|dir| {
+        let appender = RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(app)
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(&dir)
+            .map_err(|e| eprintln!("Lokitiedostoa ei voitu avata ({}): {}", dir.display(), e))
+            .ok()?;
+        let (writer, g) = tracing_appender::non_blocking(appender);
+        guard = Some(g);
+        Some(tfmt::layer().with_writer(writer).with_ansi(false))
+    });
+    let stderr_layer = stderr.then(|| tfmt::layer().with_writer(std::io::stderr));
+
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(BufferLayer { buffer: buffer.clone() })
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init();
+
+    LogHandle { buffer, _guard: guard }
+}
@@ -1,12 +1,8 @@
-mod models;
-mod network;
-mod settings;
-mod ui;
-
-use crate::models::StopData;
+use bussivahti_pro::models::{self, StopId, StopState};
+use bussivahti_pro::{logging, network, settings, source, ui};
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -15,13 +11,22 @@ use std::{collections::HashMap, io, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
 // Määritellään sovelluksen tila: Jaettu (Arc), Turvallinen (RwLock)
-type AppState = Arc<RwLock<HashMap<String, StopData>>>;
+type AppState = Arc<RwLock<HashMap<StopId, StopState>>>;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // 1. Asetusten lataus
-    let settings = settings::Settings::new().expect("Virhe: Settings.toml puuttuu tai on viallinen!");
-    let stop_order: Vec<String> = settings.stops.keys().cloned().collect(); 
+    let settings = settings::Settings::new(settings::config_arg().as_deref()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let log_handle = logging::init(&settings.logging, "bussivahti-pro", false);
+    for warning in &settings.warnings {
+        tracing::warn!("Asetukset: {}", warning);
+    }
+    let log = log_handle.buffer.clone();
+    let departure_source = source::from_settings(&settings)?;
+    tracing::info!(stops = settings.stops.len(), region = %settings.region.name, config = ?settings.path, "Käynnistetään lähtötaulu");
 
     // 2. Terminaalin alustus (Paniikkikoukku estää terminaalin jumiutumisen virhetilanteessa)
     let original_hook = std::panic::take_hook();
@@ -43,20 +48,28 @@ async fn main() -> Result<()> {
     // 4. Taustatyö (Background Worker) - Hakee datan
     let state_clone = app_state.clone();
     let settings_clone = settings.clone();
+    let control = Arc::new(network::PollControl::default());
+    let poll_control = control.clone();
     tokio::spawn(async move {
+        let mut backoff = network::PollBackoff::new(&settings_clone);
         loop {
             // Hae data
-            let new_data = network::fetch_all_stops(&settings_clone).await;
+            let new_data = network::fetch_all_stops(departure_source.as_ref(), &settings_clone.stops).await;
+            let delay = backoff.next_delay(&new_data);
             // Kirjoita data tilaan
             {
                 let mut w = state_clone.write().await;
-                *w = new_data;
+                let now = models::now();
+                for (id, result) in new_data {
+                    w.entry(id).or_default().apply(result, now);
+                }
             }
-            tokio::time::sleep(Duration::from_secs(settings_clone.update_interval)).await;
+            poll_control.wait(delay).await;
         }
     });
 
     // 5. UI Loop (Main Thread)
+    let mut tui = ui::TuiState::new(&settings);
     let tick_rate = Duration::from_millis(250);
     let mut last_tick = std::time::Instant::now();
 
@@ -64,16 +77,21 @@ async fn main() -> Result<()> {
         // Renderöinti
         {
             let data = app_state.read().await;
-            terminal.draw(|f| ui::render(f, &data, &stop_order))?;
+            let log_lines = tui.show_log.then(|| log.lines());
+            terminal.draw(|f| ui::render(f, &data, &settings.stops, log_lines.as_deref(), &mut tui))?;
         }
 
         // Syötteen luku
         let timeout = tick_rate.checked_sub(last_tick.elapsed()).unwrap_or_else(|| Duration::from_secs(0));
         if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if let KeyCode::Char('q') = key.code {
-                    break;
+            match tui.handle_event(&event::read()?) {
+                Some(ui::Command::Quit) => break,
+                Some(ui::Command::Refresh) => control.refresh_now(),
+                Some(ui::Command::Pause(paused)) => {
+                    tracing::info!(paused, "Päivitys {}", if paused { "keskeytetty" } else { "jatkuu" });
+                    control.set_paused(paused);
                 }
+                None => {}
             }
         }
 
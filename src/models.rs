@@ -1,15 +1,144 @@
-use serde::Deserialize;
+use chrono::{DateTime, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// Kaikki ajat näytetään Suomen ajassa koneen aikavyöhykkeestä riippumatta
+pub const TIMEZONE: Tz = chrono_tz::Europe::Helsinki;
+
+pub fn now() -> DateTime<Tz> {
+    chrono::Utc::now().with_timezone(&TIMEZONE)
+}
+
+// --- PYSÄKKITUNNUS ---
+
+// Pysäkin GTFS-tunnus muodossa "syöte:tunnus", esim. "tampere:0802"
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct StopId(String);
+
+impl StopId {
+    // Hyväksyy muodot:
+    //   "tampere:0802"                             GTFS-tunnus
+    //   "GTFS:tampere:0802#0802"                   geokoodauksen id
+    //   "gtfstampere:stop:GTFS:tampere:0802#0802"  geokoodauksen gid
+    //   "0802" + default_feed                      pelkkä pysäkkikoodi alueen kanssa
+    pub fn parse(text: &str, default_feed: Option<&str>) -> Result<StopId, StopIdError> {
+        let text = text.trim();
+        let text = text.split_once(":stop:").map_or(text, |(_, rest)| rest);
+        let text = text.strip_prefix("GTFS:").unwrap_or(text);
+        let text = text.split('#').next().unwrap_or(text);
+        if text.is_empty() {
+            return Err(StopIdError::Empty);
+        }
+
+        match text.split_once(':') {
+            Some((feed, code)) => Self::from_parts(feed, code),
+            None => match default_feed.filter(|f| !f.is_empty()) {
+                Some(feed) => Self::from_parts(feed, text),
+                None => Err(StopIdError::MissingFeed(text.to_string())),
+            },
+        }
+    }
+
+    fn from_parts(feed: &str, code: &str) -> Result<StopId, StopIdError> {
+        let valid = |s: &str| !s.is_empty() && !s.chars().any(char::is_whitespace);
+        if !valid(feed) || !valid(code) {
+            return Err(StopIdError::Invalid(format!("{}:{}", feed, code)));
+        }
+        Ok(StopId(format!("{}:{}", feed, code)))
+    }
+
+    // Syötteen tunnus, esim. "tampere"
+    pub fn feed(&self) -> &str {
+        self.0.split_once(':').map_or("", |(feed, _)| feed)
+    }
+
+    // Tunnus ilman syötettä, esim. "0802"
+    pub fn code(&self) -> &str {
+        self.0.split_once(':').map_or(&self.0, |(_, code)| code)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for StopId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for StopId {
+    type Err = StopIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StopId::parse(s, None)
+    }
+}
+
+impl TryFrom<String> for StopId {
+    type Error = StopIdError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<StopId> for String {
+    fn from(id: StopId) -> Self {
+        id.0
+    }
+}
+
+// HashMap<StopId, _> voidaan hakea myös pelkällä &str-avaimella
+impl Borrow<str> for StopId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopIdError {
+    Empty,
+    MissingFeed(String),
+    Invalid(String),
+}
+
+impl fmt::Display for StopIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopIdError::Empty => write!(f, "tyhjä pysäkkitunnus"),
+            StopIdError::MissingFeed(code) => write!(f, "pysäkkitunnukselta \"{}\" puuttuu syöte (esim. tampere:{})", code, code),
+            StopIdError::Invalid(id) => write!(f, "virheellinen pysäkkitunnus \"{}\"", id),
+        }
+    }
+}
+
+impl std::error::Error for StopIdError {}
 
 // --- DIGITRANSIT ROUTING API (Aikataulut) ---
 
+// Yhdistetty kysely: jokainen pysäkki omalla aliaksellaan (s0, s1, ...)
 #[derive(Deserialize, Debug, Clone)]
 pub struct GqlResponse {
-    pub data: DataData,
+    pub data: HashMap<String, Option<Stop>>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct DataData {
-    pub stop: Option<Stop>,
+// Yhden pysäkin hakuehdot lähteelle
+#[derive(Debug, Clone)]
+pub struct StopRequest {
+    pub stop_id: StopId,
+    // Unix-aika, josta lähtöjä haetaan (0 = nyt)
+    pub start_time: i64,
+    // Hakuikkunan pituus sekunteina
+    pub time_range: u64,
+    // Lähtöjä per reitin suunta (pattern), jotta linjasuodatus ei tyhjennä listaa
+    pub departures_per_pattern: usize,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -17,10 +146,59 @@ pub struct Stop {
     pub name: String,
     pub lat: f64,
     pub lon: f64,
-    #[serde(rename = "stoptimesWithoutPatterns")]
+    // Tiedostolähteet käyttävät litteää listaa, rajapinta palauttaa patterneittain
+    #[serde(rename = "stoptimesWithoutPatterns", default)]
+    pub stoptimes: Vec<StopTime>,
+    #[serde(rename = "stoptimesForPatterns", default)]
+    pub patterns: Vec<PatternStoptimes>,
+    // Pysäkkiä itseään koskevat häiriötiedotteet
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+    // Pysäkin kautta kulkevat linjat ja niiden tiedotteet
+    #[serde(default)]
+    pub routes: Vec<RouteAlerts>,
+}
+
+impl Stop {
+    pub fn all_stoptimes(self) -> impl Iterator<Item = StopTime> {
+        self.stoptimes.into_iter().chain(self.patterns.into_iter().flat_map(|p| p.stoptimes))
+    }
+
+    pub fn stoptimes_mut(&mut self) -> impl Iterator<Item = &mut StopTime> {
+        self.stoptimes.iter_mut().chain(self.patterns.iter_mut().flat_map(|p| p.stoptimes.iter_mut()))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatternStoptimes {
     pub stoptimes: Vec<StopTime>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct RouteAlerts {
+    #[serde(rename = "shortName")]
+    pub short_name: String,
+    #[serde(default)]
+    pub alerts: Vec<Alert>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Alert {
+    pub id: Option<String>,
+    #[serde(rename = "alertHeaderText")]
+    pub header: Option<String>,
+    #[serde(rename = "alertDescriptionText")]
+    pub description: Option<String>,
+    // INFO / WARNING / SEVERE / UNKNOWN_SEVERITY
+    #[serde(rename = "alertSeverityLevel")]
+    pub severity: Option<String>,
+    // Unix-aikoja (sekunteja)
+    #[serde(rename = "effectiveStartDate")]
+    pub start: Option<i64>,
+    #[serde(rename = "effectiveEndDate")]
+    pub end: Option<i64>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct StopTime {
     #[serde(rename = "realtimeDeparture")]
@@ -28,9 +206,64 @@ pub struct StopTime {
     #[serde(rename = "scheduledDeparture")]
     pub scheduled_departure: i64,
     pub realtime: bool,
+    // Palvelupäivän alku unix-aikana (GTFS: "keskipäivä miinus 12 h"). Pakollinen:
+    // puuttuva arvo olisi 1970 ja kaikki lähdöt suodattuisivat hiljaa pois.
+    #[serde(rename = "serviceDay")]
+    pub service_day: i64,
+    // SCHEDULED / UPDATED / CANCELED / ADDED / MODIFIED
+    #[serde(rename = "realtimeState", default)]
+    pub realtime_state: Option<String>,
+    // SCHEDULED / NONE / CALL_AGENCY / COORDINATE_WITH_DRIVER
+    #[serde(rename = "pickupType", default)]
+    pub pickup_type: Option<String>,
+    // Lähtöpylväs/laituri, jolta tämä vuoro lähtee (asemilla useita)
+    #[serde(default)]
+    pub stop: Option<StopPole>,
     pub trip: Trip,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopPole {
+    #[serde(rename = "platformCode")]
+    pub platform_code: Option<String>,
+    pub code: Option<String>,
+}
+
+impl StopTime {
+    // Ennustettu lähtöaika jos reaaliaikaa on, muuten aikataulun mukainen
+    pub fn departure_time(&self) -> DateTime<Tz> {
+        let seconds = if self.realtime { self.realtime_departure } else { self.scheduled_departure };
+        self.time_of_day(seconds)
+    }
+
+    pub fn status(&self) -> DepartureStatus {
+        match (self.realtime_state.as_deref(), self.pickup_type.as_deref()) {
+            (Some("CANCELED"), _) => DepartureStatus::Cancelled,
+            (_, Some("NONE")) => DepartureStatus::NoPickup,
+            (Some("ADDED"), _) => DepartureStatus::Added,
+            (Some("UPDATED" | "MODIFIED"), _) => DepartureStatus::Updated,
+            _ => DepartureStatus::Scheduled,
+        }
+    }
+
+    pub fn scheduled_time(&self) -> DateTime<Tz> {
+        self.time_of_day(self.scheduled_departure)
+    }
+
+    // GTFS-ajat voivat ylittää 24 h (esim. 25:10), joten lasketaan aina palvelupäivän alusta
+    fn time_of_day(&self, seconds: i64) -> DateTime<Tz> {
+        TIMEZONE.timestamp_opt(self.service_day + seconds, 0).unwrap()
+    }
+}
+
+// Palvelupäivän alku annetulle päivälle. Kesäaikapäivinä tämä ei ole keskiyö,
+// vaan keskipäivä miinus 12 tuntia, jolloin aikataulun kellonajat osuvat oikein.
+pub fn service_day_start(date: NaiveDate) -> i64 {
+    let noon = date.and_hms_opt(12, 0, 0).unwrap();
+    let noon = TIMEZONE.from_local_datetime(&noon).unwrap();
+    noon.timestamp() - 12 * 3600
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Trip {
     pub route: Route,
@@ -44,26 +277,399 @@ pub struct Route {
     pub short_name: String,
 }
 
+// Pysäkin linjat (lisäysikkunan valinnat)
+#[derive(Debug, Deserialize)]
+pub struct StopLinesResponse {
+    pub data: StopLinesData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StopLinesData {
+    pub stop: Option<StopLines>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StopLines {
+    pub routes: Vec<Route>,
+}
+
+// --- LÄHIPYSÄKIT ---
+
+#[derive(Deserialize, Debug)]
+pub struct NearbyResponse {
+    pub data: NearbyData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NearbyData {
+    #[serde(rename = "stopsByRadius")]
+    pub stops_by_radius: Option<NearbyConnection>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NearbyConnection {
+    pub edges: Vec<NearbyEdge>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NearbyEdge {
+    pub node: NearbyNode,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NearbyNode {
+    // Metreinä haun keskipisteestä
+    pub distance: u32,
+    pub stop: Option<NearbyApiStop>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NearbyApiStop {
+    #[serde(rename = "gtfsId")]
+    pub gtfs_id: StopId,
+    pub name: String,
+    pub code: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    #[serde(default)]
+    pub routes: Vec<Route>,
+}
+
+// Valmis lista käyttöliittymille: lähin ensin, linjat aakkosjärjestyksessä
+#[derive(Debug, Clone)]
+pub struct NearbyStop {
+    pub gtfs_id: StopId,
+    pub name: String,
+    pub code: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub distance: u32,
+    pub lines: Vec<String>,
+}
+
+// --- REITTIEN MUODOT (Kartta) ---
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RoutesResponse {
+    pub data: HashMap<String, Option<StopRoutes>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StopRoutes {
+    pub routes: Vec<RouteShape>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RouteShape {
+    #[serde(rename = "gtfsId")]
+    pub gtfs_id: String,
+    #[serde(rename = "shortName")]
+    pub short_name: String,
+    // Heksana ilman #-merkkiä, esim. "DA2128"
+    pub color: Option<String>,
+    pub patterns: Vec<PatternShape>,
+}
+
+impl RouteShape {
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        hex_rgb(self.color.as_deref()?)
+    }
+}
+
+// Rajapinnan värit ovat heksoja ilman #-merkkiä, esim. "DA2128"
+pub fn hex_rgb(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatternShape {
+    pub code: String,
+    pub headsign: Option<String>,
+    #[serde(default)]
+    pub geometry: Vec<Coordinate>,
+    #[serde(default)]
+    pub stops: Vec<PatternStop>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Coordinate {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatternStop {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+
 // --- SISÄINEN TIETORAKENNE (UI) ---
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepartureStatus {
+    Scheduled,
+    Updated,
+    Cancelled,
+    Added,
+    // Vain jättö: pysäkiltä ei voi nousta kyytiin
+    NoPickup,
+}
+
+impl DepartureStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DepartureStatus::Scheduled => "aikataulu",
+            DepartureStatus::Updated => "reaaliaika",
+            DepartureStatus::Cancelled => "PERUTTU",
+            DepartureStatus::Added => "lisävuoro",
+            DepartureStatus::NoPickup => "vain jättö",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct DepartureInfo {
     pub line: String,
     pub headsign: String,
-    pub time_str: String,
-    pub minutes_left: i64,
-    pub seconds_left: i64,
     pub is_realtime: bool,
+    pub scheduled_time: DateTime<Tz>,
+    // Ennustettu lähtöhetki; jäljellä oleva aika lasketaan tästä joka piirrolla
+    pub predicted_time: DateTime<Tz>,
+    // Etumerkillinen poikkeama aikataulusta sekunteina (+ = myöhässä)
+    pub delay: i64,
+    pub status: DepartureStatus,
+    pub platform: Option<String>,
+}
+
+impl DepartureInfo {
+    pub fn seconds_left(&self, now: DateTime<Tz>) -> i64 {
+        self.predicted_time.signed_duration_since(now).num_seconds()
+    }
+
+    pub fn minutes_left(&self, now: DateTime<Tz>) -> i64 {
+        self.seconds_left(now) / 60
+    }
+
+    // Lähtenyt pudotetaan näkyvistä heti, ei vasta seuraavalla haulla
+    pub fn has_departed(&self, now: DateTime<Tz>) -> bool {
+        self.predicted_time < now
+    }
+
+    // Poikkeama pyöristettynä minuuteiksi; 0 jos alle puoli minuuttia
+    pub fn delay_minutes(&self) -> i64 {
+        (self.delay as f64 / 60.0).round() as i64
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.status == DepartureStatus::Cancelled
+    }
+
+    // Kyytiin pääsee: ei peruttu eikä pelkkä jättöpysäkki
+    pub fn is_boardable(&self) -> bool {
+        !matches!(self.status, DepartureStatus::Cancelled | DepartureStatus::NoPickup)
+    }
+}
+
+impl StopData {
+    // Vielä lähtemättömät lähdöt aikajärjestyksessä
+    pub fn upcoming(&self, now: DateTime<Tz>) -> impl Iterator<Item = &DepartureInfo> {
+        self.departures.iter().filter(move |d| !d.has_departed(now))
+    }
+
+    // Seuraava lähtö, johon oikeasti pääsee kyytiin (karttamerkin väri)
+    pub fn next_boardable(&self, now: DateTime<Tz>) -> Option<&DepartureInfo> {
+        self.upcoming(now).find(|d| d.is_boardable())
+    }
+
+    // Voimassa olevat tiedotteet vakavin ensin; vanhentuneet piilotetaan
+    pub fn active_alerts(&self, now: DateTime<Tz>) -> Vec<&ServiceAlert> {
+        let mut alerts: Vec<&ServiceAlert> = self.alerts.iter().filter(|a| a.is_active(now)).collect();
+        alerts.sort_by_key(|a| std::cmp::Reverse(a.severity));
+        alerts
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertSeverity {
+    Info,
+    Unknown,
+    Warning,
+    Severe,
+}
+
+impl AlertSeverity {
+    pub fn from_api(level: Option<&str>) -> Self {
+        match level {
+            Some("INFO") => AlertSeverity::Info,
+            Some("WARNING") => AlertSeverity::Warning,
+            Some("SEVERE") => AlertSeverity::Severe,
+            _ => AlertSeverity::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceAlert {
+    pub id: String,
+    pub header: String,
+    pub description: String,
+    pub severity: AlertSeverity,
+    pub start: Option<DateTime<Tz>>,
+    pub end: Option<DateTime<Tz>>,
+    // Linjat, joita tiedote koskee. Tyhjä = koskee koko pysäkkiä.
+    pub lines: Vec<String>,
+}
+
+impl ServiceAlert {
+    pub fn from_api(alert: Alert) -> Self {
+        let to_time = |ts: Option<i64>| ts.and_then(|t| TIMEZONE.timestamp_opt(t, 0).single());
+        let header = alert.header.unwrap_or_default();
+        Self {
+            id: alert.id.unwrap_or_else(|| header.clone()),
+            description: alert.description.unwrap_or_default(),
+            severity: AlertSeverity::from_api(alert.severity.as_deref()),
+            start: to_time(alert.start),
+            end: to_time(alert.end),
+            lines: Vec::new(),
+            header,
+        }
+    }
+
+    // Voimassa juuri nyt: alkanut eikä vielä päättynyt
+    pub fn is_active(&self, now: DateTime<Tz>) -> bool {
+        self.start.is_none_or(|s| s <= now) && self.end.is_none_or(|e| e > now)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Tz>) -> bool {
+        self.end.is_some_and(|e| e <= now)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct StopData {
-    pub stop_id: String,
+    pub stop_id: StopId,
     pub stop_name: String,
     pub lat: f64,
     pub lon: f64,
     pub departures: Vec<DepartureInfo>,
-    pub last_updated: chrono::DateTime<chrono::Local>,
+    pub alerts: Vec<ServiceAlert>,
+    pub last_updated: DateTime<Tz>,
+}
+
+// --- HAKUVIRHEET ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchErrorKind {
+    Auth,
+    NotFound,
+    Http(u16),
+    Parse,
+    Timeout,
+    Network,
+}
+
+impl fmt::Display for FetchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchErrorKind::Auth => write!(f, "API-avain hylätty"),
+            FetchErrorKind::NotFound => write!(f, "Pysäkkiä ei löydy"),
+            FetchErrorKind::Http(code) => write!(f, "HTTP {}", code),
+            FetchErrorKind::Parse => write!(f, "Virheellinen vastaus"),
+            FetchErrorKind::Timeout => write!(f, "Aikakatkaisu"),
+            FetchErrorKind::Network => write!(f, "Verkkovirhe"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchError {
+    pub kind: FetchErrorKind,
+    pub message: String,
+    // Palvelimen Retry-After (429/503), sekunteina
+    pub retry_after: Option<u64>,
+}
+
+impl FetchError {
+    pub fn new(kind: FetchErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), retry_after: None }
+    }
+
+    pub fn from_status(code: u16, message: impl Into<String>) -> Self {
+        let kind = match code {
+            401 | 403 => FetchErrorKind::Auth,
+            404 => FetchErrorKind::NotFound,
+            code => FetchErrorKind::Http(code),
+        };
+        Self::new(kind, message)
+    }
+
+    // Ohimenevä vika, jonka uudelleenyritys voi korjata
+    pub fn is_transient(&self) -> bool {
+        match self.kind {
+            FetchErrorKind::Timeout | FetchErrorKind::Network => true,
+            FetchErrorKind::Http(code) => code == 429 || code >= 500,
+            _ => false,
+        }
+    }
+
+    pub fn not_found(stop_id: &StopId) -> Self {
+        Self::new(FetchErrorKind::NotFound, format!("Stop not found: {}", stop_id))
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+impl From<serde_json::Error> for FetchError {
+    fn from(e: serde_json::Error) -> Self {
+        FetchError::new(FetchErrorKind::Parse, e.to_string())
+    }
+}
+
+// --- PYSÄKIN TILA (UI) ---
+
+#[derive(Debug, Clone)]
+pub struct StopError {
+    pub kind: FetchErrorKind,
+    pub message: String,
+    pub at: DateTime<Tz>,
+}
+
+// Viimeisin onnistunut data säilyy, vaikka seuraava haku epäonnistuisi
+#[derive(Debug, Clone, Default)]
+pub struct StopState {
+    pub data: Option<StopData>,
+    pub error: Option<StopError>,
+}
+
+impl StopState {
+    pub fn apply(&mut self, result: Result<StopData, FetchError>, now: DateTime<Tz>) {
+        match result {
+            Ok(data) => {
+                self.data = Some(data);
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(StopError { kind: e.kind, message: e.message, at: now });
+            }
+        }
+    }
+
+    // Data on näkyvissä, mutta viimeisin päivitys epäonnistui
+    pub fn is_stale(&self) -> bool {
+        self.data.is_some() && self.error.is_some()
+    }
 }
 
 // --- GEOCODING API (Haku) - KORJATTU ---
@@ -76,6 +682,14 @@ pub struct GeoResponse {
 #[derive(Deserialize, Debug, Clone)]
 pub struct GeoFeature {
     pub properties: GeoProperties,
+    #[serde(default)]
+    pub geometry: Option<GeoGeometry>,
+}
+
+// GeoJSON-piste: coordinates = [lon, lat]
+#[derive(Deserialize, Debug, Clone)]
+pub struct GeoGeometry {
+    pub coordinates: [f64; 2],
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -91,6 +705,19 @@ pub struct GeoProperties {
     pub addendum: Option<GeoAddendum>,
 }
 
+impl GeoProperties {
+    // Geokoodauksen id, tai sen puuttuessa pysäkkikoodi alueen syötteellä
+    pub fn stop_id(&self, default_feed: &str) -> Result<StopId, StopIdError> {
+        match &self.gtfs_id {
+            Some(id) => StopId::parse(id, Some(default_feed)),
+            None => {
+                let code = self.addendum.as_ref().and_then(|a| a.gtfs.as_ref()).and_then(|g| g.code.as_deref());
+                StopId::parse(code.unwrap_or(""), Some(default_feed))
+            }
+        }
+    }
+}
+
 // Uudet apurakenteet koodin kaivamiseen
 #[derive(Deserialize, Debug, Clone)]
 pub struct GeoAddendum {
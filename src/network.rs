@@ -1,114 +1,532 @@
+use crate::logging::API_DUMP;
 use crate::models::*;
-use crate::settings::Settings;
+use crate::settings::{Region, RetryPolicy, Settings, StopConfig};
+use crate::source::DepartureSource;
 use anyhow::Result;
-use chrono::{Local, TimeZone};
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono_tz::Tz;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{debug, trace, warn};
 
-const API_URL: &str = "https://api.digitransit.fi/routing/v2/waltti/gtfs/v1";
+// Montako pysäkkiä mahtuu yhteen kyselyyn. Isommat joukot pilkotaan osiin.
+pub const MAX_STOPS_PER_QUERY: usize = 10;
+
+const ALERT_FIELDS: &str = "id alertHeaderText alertDescriptionText alertSeverityLevel effectiveStartDate effectiveEndDate";
+
+const STOPTIME_FIELDS: &str = r#"
+        realtimeDeparture
+        scheduledDeparture
+        realtime
+        realtimeState
+        pickupType
+        serviceDay
+        stop { platformCode code }
+        trip { route { shortName } tripHeadsign }"#;
 
 #[derive(Serialize)]
 struct GqlQuery {
     query: String,
 }
 
-pub async fn fetch_all_stops(settings: &Settings) -> HashMap<String, StopData> {
-    let client = Client::new();
-    let mut tasks = vec![];
-    
-    let stops_config = settings.stops.clone();
-    let api_key = Arc::new(settings.api_key.clone());
+// Jokaiselle näkyvälle pysäkille palautetaan joko data tai syy, miksi sitä ei saatu
+pub async fn fetch_all_stops(source: &dyn DepartureSource, stops: &[StopConfig]) -> HashMap<StopId, Result<StopData, FetchError>> {
+    let now = now();
+    let visible = || stops.iter().filter(|s| !s.hidden);
+    let requests: Vec<StopRequest> = visible()
+        .map(|config| stop_request(config, now))
+        .collect();
 
-    for (stop_id, wanted_lines) in stops_config {
-        let client = client.clone();
-        let key = api_key.clone();
-        let id_clone = stop_id.clone();
+    let mut fetched = source.fetch_stops(&requests).await;
+    let mut results = HashMap::new();
+    for config in visible() {
+        let id = &config.id;
+        let result = fetched
+            .remove(id)
+            .unwrap_or_else(|| Err(FetchError::not_found(id)))
+            .map(|stop| build_stop_data(stop, config, now));
+        if let Err(e) = &result {
+            warn!(stop = %id, error = %e, "Pysäkin haku epäonnistui");
+        }
+        results.insert(id.clone(), result);
+    }
+    debug!(stops = results.len(), ok = results.values().filter(|r| r.is_ok()).count(), "Lähdöt päivitetty");
+    results
+}
 
-        tasks.push(tokio::spawn(async move {
-            let data = fetch_stop(&client, &key, &id_clone, &wanted_lines).await;
-            (id_clone, data)
-        }));
+// Jokainen pattern palauttaa vähintään niin monta lähtöä kuin näytetään, joten
+// harvinainenkin linja saa omat lähtönsä vaikka pysäkillä kulkisi kymmeniä muita.
+pub fn stop_request(config: &StopConfig, now: DateTime<Tz>) -> StopRequest {
+    let start_time = if config.start_offset() > 0 {
+        now.timestamp() + config.start_offset() as i64 * 60
+    } else {
+        0
+    };
+    StopRequest {
+        stop_id: config.id.clone(),
+        start_time,
+        time_range: config.lookahead_minutes * 60,
+        departures_per_pattern: config.per_line.unwrap_or(0).max(config.max_departures),
     }
+}
 
-    let mut results = HashMap::new();
-    for task in futures::future::join_all(tasks).await {
-        if let Ok((id, Ok(stop_data))) = task {
-            results.insert(id, stop_data);
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        let kind = if e.is_timeout() {
+            FetchErrorKind::Timeout
+        } else if let Some(status) = e.status() {
+            return FetchError::from_status(status.as_u16(), e.to_string());
+        } else if e.is_decode() {
+            FetchErrorKind::Parse
+        } else {
+            FetchErrorKind::Network
+        };
+        FetchError::new(kind, e.to_string())
+    }
+}
+
+// --- DIGITRANSIT ---
+
+pub struct DigitransitSource {
+    client: Client,
+    api_key: String,
+    api_url: String,
+    retry: RetryPolicy,
+}
+
+// Yhteinen HTTP-asiakas kaikille Digitransit-kutsuille, aikakatkaisu [retry]-asetuksista.
+// Client on sisäisesti Arc, joten kloonaus tehtäviin on halpaa.
+pub fn client(retry: &RetryPolicy) -> Client {
+    Client::builder()
+        .timeout(Duration::from_secs(retry.timeout_secs))
+        .build()
+        .expect("HTTP-asiakkaan luonti epäonnistui")
+}
+
+impl DigitransitSource {
+    pub fn new(api_key: &str, api_url: &str, retry: &RetryPolicy) -> Self {
+        Self {
+            client: client(retry),
+            api_key: api_key.to_string(),
+            api_url: api_url.to_string(),
+            retry: retry.clone(),
+        }
+    }
+
+    // Yksi kysely koko pysäkkijoukolle, ohimenevät virheet yritetään uudelleen
+    async fn fetch_chunk(&self, requests: &[StopRequest]) -> Result<HashMap<String, Option<Stop>>, FetchError> {
+        let query = build_batch_query(requests);
+        let mut attempt = 0;
+
+        loop {
+            match post_graphql::<GqlResponse>(&self.client, &self.api_url, &self.api_key, &query).await {
+                Ok(resp) => return Ok(resp.data),
+                Err(e) if e.is_transient() && attempt < self.retry.max_retries => {
+                    // Palvelimen Retry-After sellaisenaan; vain oma backoff on katettu
+                    let wait = e.retry_after
+                        .map(Duration::from_secs)
+                        .unwrap_or_else(|| backoff_delay(&self.retry, attempt));
+                    warn!(error = %e, attempt, wait_ms = wait.as_millis() as u64, "Kysely epäonnistui, yritetään uudelleen");
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    warn!(error = %e, stops = requests.len(), "Pysäkkikysely epäonnistui");
+                    return Err(e);
+                }
+            }
         }
     }
-    results
 }
 
-async fn fetch_stop(client: &Client, api_key: &str, stop_id: &str, wanted_lines: &[String]) -> Result<StopData> {
-    let query = format!(
-        r#"{{
-          stop(id: "{}") {{
-            name
-            lat
-            lon
-            stoptimesWithoutPatterns(numberOfDepartures: 20) {{
-              realtimeDeparture
-              scheduledDeparture
-              realtime
-              trip {{ route {{ shortName }} tripHeadsign }}
-            }}
-          }}
-        }}"#,
-        stop_id
-    );
+// --- UUDELLEENYRITYS & BACKOFF ---
 
-    let resp = client.post(API_URL)
-        .header("digitransit-subscription-key", api_key)
-        .header("Content-Type", "application/json")
-        .json(&GqlQuery { query })
-        .send().await?
-        .json::<GqlResponse>().await?;
+// Retry-After sekunteina; HTTP-päivämäärämuotoa ei tueta (palauttaa None)
+pub fn retry_after(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+// Eksponentiaalinen odotus puolikkaalla jitterillä (equal jitter): satunnainen
+// väliltä [d/2, d], missä d = base * 2^attempt katettuna backoff_max_secs:iin
+pub fn backoff_delay(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let base = policy.backoff_base_ms.saturating_mul(1u64 << attempt.min(20));
+    let capped = Duration::from_millis(base).min(max_backoff(policy));
+    let half = capped / 2;
+    half + rand::thread_rng().gen_range(Duration::ZERO..=half)
+}
+
+fn max_backoff(policy: &RetryPolicy) -> Duration {
+    Duration::from_secs(policy.backoff_max_secs)
+}
+
+// Päivityssilmukan tahdistus: normaalisti update_interval, mutta jos rajapinta
+// on kokonaan alhaalla, odotus kasvaa eksponentiaalisesti (tai Retry-Afterin mukaan).
+pub struct PollBackoff {
+    interval: Duration,
+    policy: RetryPolicy,
+    failures: u32,
+}
+
+impl PollBackoff {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            interval: Duration::from_secs(settings.update_interval),
+            policy: settings.retry.clone(),
+            failures: 0,
+        }
+    }
+
+    pub fn next_delay(&mut self, results: &HashMap<StopId, Result<StopData, FetchError>>) -> Duration {
+        let api_down = !results.is_empty()
+            && results.values().all(|r| matches!(r, Err(e) if e.is_transient()));
+
+        if !api_down {
+            self.failures = 0;
+            return self.interval;
+        }
+
+        let retry_after = results.values()
+            .filter_map(|r| r.as_ref().err().and_then(|e| e.retry_after))
+            .max()
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let delay = backoff_delay(&self.policy, self.failures).max(retry_after).max(self.interval);
+        self.failures = self.failures.saturating_add(1);
+        delay
+    }
+}
+
+// Lähtötaulun ohjaus päivityssilmukalle: tauko (p) ja pakotettu päivitys (r)
+#[derive(Default)]
+pub struct PollControl {
+    paused: AtomicBool,
+    refresh: Notify,
+}
+
+impl PollControl {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    // Jatkettaessa haetaan heti, ei vasta seuraavan odotuksen jälkeen
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        if !paused {
+            self.refresh.notify_one();
+        }
+    }
+
+    pub fn refresh_now(&self) {
+        self.refresh.notify_one();
+    }
+
+    // Odottaa seuraavaa hakua: viive kulunut (ellei tauolla) tai päivitystä pyydetty.
+    // Pakotettu päivitys hakee tauollakin.
+    pub async fn wait(&self, delay: Duration) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => if !self.is_paused() { return },
+                _ = self.refresh.notified() => return,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DepartureSource for DigitransitSource {
+    async fn fetch_stops(&self, requests: &[StopRequest]) -> HashMap<StopId, Result<Stop, FetchError>> {
+        let chunks = requests.chunks(MAX_STOPS_PER_QUERY);
+        let responses = futures::future::join_all(chunks.clone().map(|c| self.fetch_chunk(c))).await;
+
+        let mut results = HashMap::new();
+        for (chunk, response) in chunks.zip(responses) {
+            match response {
+                Ok(data) => results.extend(resolve_aliases(chunk, data)),
+                Err(e) => {
+                    // Koko osa epäonnistui: sama virhe jokaiselle sen pysäkille
+                    for req in chunk {
+                        results.insert(req.stop_id.clone(), Err(e.clone()));
+                    }
+                }
+            }
+        }
+        results
+    }
+}
+
+// Vastauksen aliakset s0, s1, ... takaisin pysäkkitunnuksiksi; puuttuva tai null = ei löydy
+pub fn resolve_aliases(chunk: &[StopRequest], mut data: HashMap<String, Option<Stop>>) -> HashMap<StopId, Result<Stop, FetchError>> {
+    chunk
+        .iter()
+        .enumerate()
+        .map(|(i, req)| {
+            let stop = data.remove(&format!("s{}", i)).flatten();
+            (req.stop_id.clone(), stop.ok_or_else(|| FetchError::not_found(&req.stop_id)))
+        })
+        .collect()
+}
 
-    let stop = resp.data.stop.ok_or(anyhow::anyhow!("Stop not found"))?;
-    let now = Local::now();
-    let today_midnight = now.date_naive().and_hms_opt(0, 0, 0).unwrap();
+// GraphQL-merkkijonoliteraali lainausmerkkeineen: ", \ ja ohjausmerkit escapetaan
+pub fn graphql_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Rakentaa aliasoidun GraphQL-dokumentin: { s0: stop(id: "...") { ... } s1: ... }
+pub fn build_batch_query(requests: &[StopRequest]) -> String {
+    let mut query = String::from("{");
+    for (i, req) in requests.iter().enumerate() {
+        query.push_str(&format!(
+            r#"
+  s{}: stop(id: {}) {{
+    name
+    lat
+    lon
+    stoptimesForPatterns(startTime: {}, timeRange: {}, numberOfDepartures: {}, omitCanceled: false) {{
+      stoptimes {{{}
+      }}
+    }}
+    alerts {{ {} }}
+    routes {{ shortName alerts {{ {} }} }}
+  }}"#,
+            i, graphql_string(req.stop_id.as_str()), req.start_time, req.time_range, req.departures_per_pattern, STOPTIME_FIELDS,
+            ALERT_FIELDS, ALERT_FIELDS
+        ));
+    }
+    query.push_str("\n}");
+    query
+}
+
+// --- MUUNNOS UI-MUOTOON ---
+
+// Suodattaa linjat ja hakuikkunan ja laskee ajat annetun hetken suhteen (testattava ilman verkkoa)
+pub fn build_stop_data(stop: Stop, config: &StopConfig, now: DateTime<Tz>) -> StopData {
+    let window_start = config.start_offset() as i64 * 60;
+    let window_end = window_start + config.lookahead_minutes as i64 * 60;
+    let name = stop.name.clone();
+    let (lat, lon) = (stop.lat, stop.lon);
+    let alerts = collect_alerts(&stop, config, now);
 
     let mut departures = Vec::new();
 
-    for st in stop.stoptimes {
+    for st in stop.all_stoptimes() {
         let line = st.trip.route.short_name.clone();
         
         // "ALL"-tuki: Hyväksy jos listalla "ALL" tai kyseinen linja
-        let accept_all = wanted_lines.contains(&"ALL".to_string());
-        if !accept_all && !wanted_lines.contains(&line) { 
+        if !config.accepts_line(&line) { 
             continue; 
         }
 
-        let seconds_from_midnight = if st.realtime { st.realtime_departure } else { st.scheduled_departure };
-        let duration = chrono::Duration::seconds(seconds_from_midnight as i64);
-        let dep_naive = today_midnight + duration;
-        let final_time = Local.from_local_datetime(&dep_naive).single().unwrap_or(now);
+        let final_time = st.departure_time();
         let seconds_left = final_time.signed_duration_since(now).num_seconds();
-        if seconds_left < 0 { continue; }
+        if seconds_left < window_start || seconds_left > window_end { continue; }
 
         departures.push(DepartureInfo {
             line,
-            headsign: st.trip.headsign,
-            time_str: final_time.format("%H:%M").to_string(),
-            minutes_left: seconds_left / 60,
-            seconds_left,
+            headsign: st.trip.headsign.clone(),
             is_realtime: st.realtime,
+            scheduled_time: st.scheduled_time(),
+            predicted_time: final_time,
+            delay: if st.realtime { st.realtime_departure - st.scheduled_departure } else { 0 },
+            status: st.status(),
+            platform: st.stop.as_ref().and_then(|s| s.platform_code.clone()),
         });
     }
 
-    departures.sort_by_key(|d| d.seconds_left);
-    departures.truncate(5);
+    departures.sort_by_key(|d| d.predicted_time);
+
+    // "Seuraavat N per linja": pidetään kunkin linjan N ensimmäistä aikajärjestyksessä
+    if let Some(n) = config.per_line {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        departures.retain(|d| {
+            let count = counts.entry(d.line.clone()).or_default();
+            *count += 1;
+            *count <= n
+        });
+    }
+    departures.truncate(config.max_departures);
 
-    Ok(StopData {
-        stop_id: stop_id.to_string(),
-        stop_name: stop.name,
-        lat: stop.lat,
-        lon: stop.lon,
+    StopData {
+        stop_id: config.id.clone(),
+        stop_name: name,
+        lat,
+        lon,
         departures,
-        last_updated: Local::now(),
-    })
+        alerts,
+        last_updated: now,
+    }
+}
+
+// Pysäkin omat tiedotteet + seurattujen linjojen tiedotteet, yhdistettynä id:n mukaan
+fn collect_alerts(stop: &Stop, config: &StopConfig, now: DateTime<Tz>) -> Vec<ServiceAlert> {
+    let mut alerts: Vec<ServiceAlert> = Vec::new();
+
+    let stop_alerts = stop.alerts.iter().map(|a| (None, a));
+    let route_alerts = stop.routes.iter()
+        .filter(|r| config.accepts_line(&r.short_name))
+        .flat_map(|r| r.alerts.iter().map(move |a| (Some(r.short_name.clone()), a)));
+
+    for (line, raw) in stop_alerts.chain(route_alerts) {
+        let mut alert = ServiceAlert::from_api(raw.clone());
+        if alert.is_expired(now) {
+            continue;
+        }
+        match alerts.iter_mut().find(|a| a.id == alert.id) {
+            // Koko pysäkin tiedote (tyhjä lista) pysyy koko pysäkin tiedotteena
+            Some(existing) => {
+                if let Some(line) = line {
+                    if !existing.lines.is_empty() && !existing.lines.contains(&line) {
+                        existing.lines.push(line);
+                    }
+                }
+            }
+            None => {
+                alert.lines = line.into_iter().collect();
+                alerts.push(alert);
+            }
+        }
+    }
+    alerts
+}
+
+// --- GRAPHQL ---
+
+// Kaikki Digitransitin GraphQL-kyselyt (pysäkit, reitit, lähipysäkit, reittiopas...).
+// Pyyntö ja vastaus kirjataan API_DUMP-kohteelle, Retry-After talletetaan virheeseen.
+pub async fn post_graphql<T: DeserializeOwned>(client: &Client, url: &str, api_key: &str, query: &str) -> Result<T, FetchError> {
+    trace!(target: API_DUMP, url, "GraphQL-pyyntö:\n{}", query);
+    let resp = client.post(url)
+        .header("digitransit-subscription-key", api_key)
+        .json(&GqlQuery { query: query.to_string() })
+        .send().await?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let mut err = FetchError::from_status(status.as_u16(), format!("HTTP {}", status));
+        err.retry_after = retry_after(resp.headers());
+        return Err(err);
+    }
+
+    let body = resp.text().await?;
+    trace!(target: API_DUMP, "GraphQL-vastaus:\n{}", body);
+    Ok(serde_json::from_str(&body)?)
+}
+
+// --- REITTIEN MUODOT ---
+
+// Hakee seurattujen pysäkkien kautta kulkevien, seurattujen linjojen reitit
+// (kaikki suunnat ja pysäkit). Sama reitti useammalta pysäkiltä palautetaan kerran.
+pub async fn fetch_route_shapes(client: &Client, api_key: &str, region: &Region, stops: &[StopConfig]) -> Result<Vec<RouteShape>, FetchError> {
+    let mut routes: Vec<RouteShape> = Vec::new();
+
+    for chunk in stops.chunks(MAX_STOPS_PER_QUERY) {
+        let mut query = String::from("{");
+        for (i, config) in chunk.iter().enumerate() {
+            query.push_str(&format!(
+                r#"
+  s{}: stop(id: {}) {{
+    routes {{
+      gtfsId shortName color
+      patterns {{ code headsign geometry {{ lat lon }} stops {{ name lat lon }} }}
+    }}
+  }}"#,
+                i, graphql_string(config.id.as_str())
+            ));
+        }
+        query.push_str("\n}");
+
+        let resp: RoutesResponse = post_graphql(client, region.api_url(), api_key, &query).await?;
+
+        for (i, config) in chunk.iter().enumerate() {
+            let Some(Some(stop)) = resp.data.get(&format!("s{}", i)) else { continue };
+            for route in &stop.routes {
+                if config.accepts_line(&route.short_name) && !routes.iter().any(|r| r.gtfs_id == route.gtfs_id) {
+                    routes.push(route.clone());
+                }
+            }
+        }
+    }
+    Ok(routes)
+}
+
+// Pysäkit annetun pisteen ympäriltä, säde metreinä
+pub async fn stops_by_radius(client: &Client, lat: f64, lon: f64, radius: u32, api_key: &str, region: &Region) -> Result<Vec<NearbyStop>, FetchError> {
+    let query = format!(
+        r#"{{
+  stopsByRadius(lat: {}, lon: {}, radius: {}, first: 30) {{
+    edges {{ node {{ distance stop {{ gtfsId name code lat lon routes {{ shortName }} }} }} }}
+  }}
+}}"#,
+        lat, lon, radius
+    );
+
+    let resp: NearbyResponse = post_graphql(client, region.api_url(), api_key, &query).await?;
+
+    let edges = resp.data.stops_by_radius.map(|c| c.edges).unwrap_or_default();
+    let mut stops: Vec<NearbyStop> = edges
+        .into_iter()
+        .filter_map(|edge| {
+            let stop = edge.node.stop?;
+            let mut lines: Vec<String> = stop.routes.into_iter().map(|r| r.short_name).collect();
+            lines.sort_by(|a, b| natural_line_order(a, b));
+            lines.dedup();
+            Some(NearbyStop {
+                gtfs_id: stop.gtfs_id,
+                name: stop.name,
+                code: stop.code,
+                lat: stop.lat,
+                lon: stop.lon,
+                distance: edge.node.distance,
+                lines,
+            })
+        })
+        .collect();
+    stops.sort_by_key(|s| s.distance);
+    Ok(stops)
+}
+
+// Pysäkin kautta kulkevat linjat järjestyksessä
+pub async fn stop_lines(client: &Client, stop_id: &StopId, api_key: &str, region: &Region) -> Result<Vec<String>, FetchError> {
+    let query = format!(r#"{{ stop(id: {}) {{ routes {{ shortName }} }} }}"#, graphql_string(stop_id.as_str()));
+    let resp: StopLinesResponse = post_graphql(client, region.api_url(), api_key, &query).await?;
+
+    let stop = resp.data.stop.ok_or_else(|| FetchError::not_found(stop_id))?;
+    let mut lines: Vec<String> = stop.routes.into_iter().map(|r| r.short_name).collect();
+    lines.sort_by(|a, b| natural_line_order(a, b));
+    lines.dedup();
+    Ok(lines)
+}
+
+// "3" ennen "10":tä ja "3B":tä, muuten aakkosjärjestys
+pub fn natural_line_order(a: &str, b: &str) -> std::cmp::Ordering {
+    let number = |s: &str| s.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse::<u32>().ok();
+    number(a).cmp(&number(b)).then_with(|| a.cmp(b))
 }
 
 // Haku (Geocoding API)
@@ -119,53 +537,49 @@ async fn fetch_stop(client: &Client, api_key: &str, stop_id: &str, wanted_lines:
 
 // src/network.rs
 
-pub async fn search_stops(text: &str, api_key: &str) -> Result<Vec<GeoProperties>> {
-    let client = Client::new();
+pub async fn search_stops(client: &Client, text: &str, api_key: &str, region: &Region) -> Result<Vec<GeoProperties>> {
+    let features = geocode(client, text, "stop", api_key, region).await?;
+    Ok(features.into_iter().map(|f| f.properties).collect())
+}
+
+// Yleinen haku: layers esim. "stop" tai "stop,venue,address,street" (reittiopas)
+pub async fn geocode(client: &Client, text: &str, layers: &str, api_key: &str, region: &Region) -> Result<Vec<GeoFeature>> {
     let url = "https://api.digitransit.fi/geocoding/v1/search";
     
-    println!("DEBUG: Haetaan hakusanalla: '{}'", text);
+    debug!(text, layers, region = %region.name, "Geokoodaushaku");
 
+    let b = region.boundary;
     let resp = client.get(url)
         .query(&[
             ("text", text),
             ("size", "10"),
             // ("sources", "gtfs"), // Tämä on poistettu, jotta löytyy kaikki
-            ("layers", "stop"),     // Rajataan pysäkkeihin
-            
-            // Tampereen aluerajaus
-            ("boundary.rect.min_lat", "61.4"),
-            ("boundary.rect.max_lat", "61.6"),
-            ("boundary.rect.min_lon", "23.5"),
-            ("boundary.rect.max_lon", "24.0"),
+            ("layers", layers),     // Rajataan pysäkkeihin
+        ])
+        // Alueen rajaus asetuksista
+        .query(&[
+            ("boundary.rect.min_lat", b.min_lat),
+            ("boundary.rect.max_lat", b.max_lat),
+            ("boundary.rect.min_lon", b.min_lon),
+            ("boundary.rect.max_lon", b.max_lon),
         ])
         .header("digitransit-subscription-key", api_key)
         .send()
         .await?;
 
-    println!("DEBUG: API vastaus status: {}", resp.status());
-
-    if !resp.status().is_success() {
-        println!("DEBUG: Virhe haussa! Status koodi: {}", resp.status());
-        if let Ok(err_text) = resp.text().await {
-             println!("DEBUG: Virheviesti: {}", err_text);
-        }
-        return Err(anyhow::anyhow!("API request failed"));
+    let status = resp.status();
+    if !status.is_success() {
+        let err_text = resp.text().await.unwrap_or_default();
+        warn!(%status, body = %err_text, "Geokoodaushaku epäonnistui");
+        return Err(anyhow::anyhow!("API request failed: HTTP {}", status));
     }
 
-    // --- DEBUGGAUS ALKAA ---
-    // 1. Luetaan vastaus tekstinä (ei suoraan JSONina), jotta voimme tulostaa sen
+    // Luetaan vastaus ensin tekstinä, jotta sen voi tarvittaessa kirjata kokonaan
     let raw_text = resp.text().await?;
-    
-    // 2. Tulostetaan terminaaliin. TÄMÄ ON SE TÄRKEIN RIVI NYT!
-    // Etsi tulosteesta kohta, jossa on pysäkin ID (esim. "gtfsId": "..." tai "id": "...")
-    println!("DEBUG RAW JSON: {}", raw_text); 
+    trace!(target: API_DUMP, "Geokoodausvastaus:\n{}", raw_text);
 
-    // 3. Parsitaan teksti JSON-olioksi
     let json: GeoResponse = serde_json::from_str(&raw_text)?;
-    // --- DEBUGGAUS PÄÄTTYY ---
-
-    println!("DEBUG: Löydetty {} tulosta", json.features.len());
+    debug!(results = json.features.len(), "Geokoodaushaku valmis");
 
-    let results = json.features.into_iter().map(|f| f.properties).collect();
-    Ok(results)
+    Ok(json.features)
 }
\ No newline at end of file
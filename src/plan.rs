@@ -0,0 +1,384 @@
+use crate::models::{hex_rgb, Coordinate, FetchError, FetchErrorKind, TIMEZONE};
+use crate::network;
+use crate::settings::Region;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use reqwest::Client;
+use serde::Deserialize;
+use std::fmt;
+
+// Reittiopas: Digitransitin planConnection-kysely tyypitettyinä matkaehdotuksina
+
+// --- HAUN PARAMETRIT ---
+
+#[derive(Debug, Clone)]
+pub struct Place {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl Place {
+    // "61.4978,23.7603" -> koordinaatit sellaisenaan, muuten None
+    pub fn from_coordinates(text: &str) -> Option<Place> {
+        let (lat, lon) = text.split_once(',')?;
+        let lat: f64 = lat.trim().parse().ok()?;
+        let lon: f64 = lon.trim().parse().ok()?;
+        Some(Place { name: format!("{:.5}, {:.5}", lat, lon), lat, lon })
+    }
+}
+
+// Lähtö- tai määränpää tekstistä: koordinaatit tai geokoodauksen paras osuma
+pub async fn resolve_place(client: &Client, text: &str, api_key: &str, region: &Region) -> Result<Place> {
+    if let Some(place) = Place::from_coordinates(text) {
+        return Ok(place);
+    }
+    let features = network::geocode(client, text, "stop,station,venue,address,street", api_key, region).await?;
+    features
+        .into_iter()
+        .find_map(|f| {
+            let [lon, lat] = f.geometry?.coordinates;
+            Some(Place { name: f.properties.label, lat, lon })
+        })
+        .ok_or_else(|| anyhow!("Paikkaa ei löytynyt: {}", text))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TimeConstraint {
+    Now,
+    DepartAt(DateTime<Tz>),
+    ArriveBy(DateTime<Tz>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitMode {
+    Bus,
+    Tram,
+    Rail,
+    Subway,
+    Ferry,
+}
+
+impl TransitMode {
+    pub fn parse(text: &str) -> Option<TransitMode> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "bus" | "bussi" => Some(Self::Bus),
+            "tram" | "ratikka" => Some(Self::Tram),
+            "rail" | "juna" => Some(Self::Rail),
+            "subway" | "metro" => Some(Self::Subway),
+            "ferry" | "lautta" => Some(Self::Ferry),
+            _ => None,
+        }
+    }
+
+    fn as_api(self) -> &'static str {
+        match self {
+            Self::Bus => "BUS",
+            Self::Tram => "TRAM",
+            Self::Rail => "RAIL",
+            Self::Subway => "SUBWAY",
+            Self::Ferry => "FERRY",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Bus => "Bussi",
+            Self::Tram => "Ratikka",
+            Self::Rail => "Juna",
+            Self::Subway => "Metro",
+            Self::Ferry => "Lautta",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlanRequest {
+    pub from: Place,
+    pub to: Place,
+    pub time: TimeConstraint,
+    // Tyhjä = kaikki kulkumuodot
+    pub modes: Vec<TransitMode>,
+    pub count: usize,
+}
+
+// "09:00" tänään Suomen aikaa. Kesäaikaan siirryttäessä puuttuva tunti (03:xx)
+// siirtyy tunnilla eteenpäin, syksyn toistuvasta tunnista valitaan ensimmäinen.
+pub fn parse_clock(text: &str, today: DateTime<Tz>) -> Option<DateTime<Tz>> {
+    let time = NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()?;
+    let local = today.date_naive().and_time(time);
+    TIMEZONE.from_local_datetime(&local).earliest()
+        .or_else(|| TIMEZONE.from_local_datetime(&(local + chrono::Duration::hours(1))).earliest())
+}
+
+// --- TULOKSET ---
+
+#[derive(Debug, Clone)]
+pub struct Itinerary {
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+    pub walk_distance: f64,
+    pub transfers: usize,
+    pub legs: Vec<Leg>,
+}
+
+impl Itinerary {
+    pub fn duration_minutes(&self) -> i64 {
+        self.end.signed_duration_since(self.start).num_minutes()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Leg {
+    // Rajapinnan kulkumuoto: WALK, BUS, TRAM, RAIL...
+    pub mode: String,
+    pub line: Option<String>,
+    pub headsign: Option<String>,
+    pub from: Place,
+    pub to: Place,
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+    pub distance: f64,
+    pub is_realtime: bool,
+    pub color: Option<(u8, u8, u8)>,
+    pub geometry: Vec<Coordinate>,
+}
+
+impl Leg {
+    pub fn is_walk(&self) -> bool {
+        self.mode == "WALK"
+    }
+}
+
+impl fmt::Display for Leg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_walk() {
+            return write!(f, "Kävely {:.0} m → {}", self.distance, self.to.name);
+        }
+        let mode: &str = match TransitMode::parse(&self.mode) {
+            Some(m) => m.label(),
+            None => &self.mode,
+        };
+        write!(f, "{} {}", mode, self.line.as_deref().unwrap_or("?"))?;
+        if let Some(headsign) = &self.headsign {
+            write!(f, " ({})", headsign)?;
+        }
+        write!(f, " {} → {}", self.from.name, self.to.name)
+    }
+}
+
+// --- KYSELY ---
+
+pub async fn plan(client: &Client, request: &PlanRequest, api_key: &str, region: &Region) -> Result<Vec<Itinerary>, FetchError> {
+    let resp: PlanResponse = network::post_graphql(client, region.api_url(), api_key, &build_plan_query(request)).await?;
+
+    let Some(data) = resp.data else {
+        let message = resp.errors.into_iter().map(|e| e.message).collect::<Vec<_>>().join("; ");
+        return Err(FetchError::new(FetchErrorKind::Parse, message));
+    };
+    data.plan_connection.edges.into_iter().map(|e| e.node.into_itinerary()).collect()
+}
+
+pub fn build_plan_query(request: &PlanRequest) -> String {
+    let location = |p: &Place| format!(
+        "{{ location: {{ coordinate: {{ latitude: {}, longitude: {} }} }}, label: {} }}",
+        p.lat, p.lon, network::graphql_string(&p.name)
+    );
+    let date_time = match request.time {
+        TimeConstraint::Now => String::new(),
+        TimeConstraint::DepartAt(t) => format!("dateTime: {{ earliestDeparture: \"{}\" }}", t.to_rfc3339()),
+        TimeConstraint::ArriveBy(t) => format!("dateTime: {{ latestArrival: \"{}\" }}", t.to_rfc3339()),
+    };
+    let modes = if request.modes.is_empty() {
+        String::new()
+    } else {
+        let list: Vec<String> = request.modes.iter().map(|m| format!("{{ mode: {} }}", m.as_api())).collect();
+        format!("modes: {{ transit: {{ transit: [{}] }} }}", list.join(", "))
+    };
+
+    format!(
+        r#"{{
+  planConnection(
+    origin: {}
+    destination: {}
+    {}
+    {}
+    first: {}
+  ) {{
+    edges {{ node {{
+      start end walkDistance numberOfTransfers
+      legs {{
+        mode distance realTime headsign
+        start {{ scheduledTime estimated {{ time }} }}
+        end {{ scheduledTime estimated {{ time }} }}
+        from {{ name lat lon }}
+        to {{ name lat lon }}
+        route {{ shortName color }}
+        legGeometry {{ points }}
+      }}
+    }} }}
+  }}
+}}"#,
+        location(&request.from), location(&request.to), date_time, modes, request.count
+    )
+}
+
+// --- RAJAPINNAN VASTAUS ---
+
+#[derive(Deserialize)]
+struct PlanResponse {
+    data: Option<PlanData>,
+    #[serde(default)]
+    errors: Vec<GqlError>,
+}
+
+#[derive(Deserialize)]
+struct GqlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct PlanData {
+    #[serde(rename = "planConnection")]
+    plan_connection: PlanConnection,
+}
+
+#[derive(Deserialize)]
+struct PlanConnection {
+    edges: Vec<PlanEdge>,
+}
+
+#[derive(Deserialize)]
+struct PlanEdge {
+    node: RawItinerary,
+}
+
+#[derive(Deserialize)]
+struct RawItinerary {
+    start: String,
+    end: String,
+    #[serde(rename = "walkDistance", default)]
+    walk_distance: f64,
+    #[serde(rename = "numberOfTransfers", default)]
+    number_of_transfers: usize,
+    legs: Vec<RawLeg>,
+}
+
+#[derive(Deserialize)]
+struct RawLeg {
+    mode: String,
+    #[serde(default)]
+    distance: f64,
+    #[serde(rename = "realTime", default)]
+    realtime: bool,
+    headsign: Option<String>,
+    start: LegTime,
+    end: LegTime,
+    from: RawPlace,
+    to: RawPlace,
+    route: Option<RawRoute>,
+    #[serde(rename = "legGeometry")]
+    geometry: Option<LegGeometry>,
+}
+
+#[derive(Deserialize)]
+struct LegTime {
+    #[serde(rename = "scheduledTime")]
+    scheduled: String,
+    estimated: Option<Estimated>,
+}
+
+#[derive(Deserialize)]
+struct Estimated {
+    time: String,
+}
+
+#[derive(Deserialize)]
+struct RawPlace {
+    name: String,
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Deserialize)]
+struct RawRoute {
+    #[serde(rename = "shortName")]
+    short_name: Option<String>,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LegGeometry {
+    points: String,
+}
+
+impl RawItinerary {
+    fn into_itinerary(self) -> Result<Itinerary, FetchError> {
+        let legs = self.legs.into_iter().map(RawLeg::into_leg).collect::<Result<Vec<_>, _>>()?;
+        Ok(Itinerary {
+            start: parse_time(&self.start)?,
+            end: parse_time(&self.end)?,
+            walk_distance: self.walk_distance,
+            transfers: self.number_of_transfers,
+            legs,
+        })
+    }
+}
+
+impl RawLeg {
+    fn into_leg(self) -> Result<Leg, FetchError> {
+        // Ennuste jos sellainen on, muuten aikataulu
+        let time = |t: &LegTime| parse_time(t.estimated.as_ref().map_or(&t.scheduled, |e| &e.time));
+        Ok(Leg {
+            start: time(&self.start)?,
+            end: time(&self.end)?,
+            line: self.route.as_ref().and_then(|r| r.short_name.clone()),
+            color: self.route.as_ref().and_then(|r| r.color.as_deref()).and_then(hex_rgb),
+            geometry: self.geometry.map(|g| decode_polyline(&g.points)).unwrap_or_default(),
+            mode: self.mode,
+            headsign: self.headsign,
+            from: Place { name: self.from.name, lat: self.from.lat, lon: self.from.lon },
+            to: Place { name: self.to.name, lat: self.to.lat, lon: self.to.lon },
+            distance: self.distance,
+            is_realtime: self.realtime,
+        })
+    }
+}
+
+fn parse_time(text: &str) -> Result<DateTime<Tz>, FetchError> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|t| t.with_timezone(&TIMEZONE))
+        .map_err(|e| FetchError::new(FetchErrorKind::Parse, format!("{}: {}", text, e)))
+}
+
+// Googlen polyline-koodaus (tarkkuus 1e-5), jota legGeometry käyttää
+pub fn decode_polyline(encoded: &str) -> Vec<Coordinate> {
+    let mut coords = Vec::new();
+    let (mut lat, mut lon) = (0i64, 0i64);
+    let mut bytes = encoded.bytes();
+
+    let mut next_value = || -> Option<i64> {
+        let (mut result, mut shift) = (0i64, 0);
+        loop {
+            // Rikkinäinen syöte: arvo ei mahdu i64:ään, palautetaan siihen asti puretut
+            if shift > 60 {
+                return None;
+            }
+            let b = bytes.next()? as i64 - 63;
+            result |= (b & 0x1f) << shift;
+            shift += 5;
+            if b < 0x20 {
+                break;
+            }
+        }
+        Some(if result & 1 != 0 { !(result >> 1) } else { result >> 1 })
+    };
+
+    while let (Some(dlat), Some(dlon)) = (next_value(), next_value()) {
+        lat += dlat;
+        lon += dlon;
+        coords.push(Coordinate { lat: lat as f64 / 1e5, lon: lon as f64 / 1e5 });
+    }
+    coords
+}
@@ -0,0 +1,354 @@
+use crate::models::StopId;
+use crate::settings::{Settings, StopConfig};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+
+// Seurattavat pysäkit ajon aikana. Pollaus ja käyttöliittymä jakavat saman listan;
+// jokainen muutos kirjoitetaan asetustiedostoon niin, että käyttäjän kommentit,
+// muotoilu ja järjestys säilyvät.
+
+#[derive(Debug)]
+pub enum RegistryError {
+    AlreadyTracked(StopId),
+    NotTracked(StopId),
+    // Muutos on voimassa, mutta sitä ei saatu tallennettua
+    Save { path: PathBuf, error: io::Error },
+    // Tiedosto on muuttunut rikkinäiseksi käynnistyksen jälkeen
+    Parse { path: PathBuf, message: String },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::AlreadyTracked(id) => write!(f, "pysäkki {} on jo seurannassa", id),
+            RegistryError::NotTracked(id) => write!(f, "pysäkki {} ei ole seurannassa", id),
+            RegistryError::Save { path, error } => write!(f, "tallennus tiedostoon {} epäonnistui: {}", path.display(), error),
+            RegistryError::Parse { path, message } => write!(f, "tiedostoa {} ei voitu päivittää: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+#[derive(Clone)]
+pub struct StopRegistry {
+    stops: Arc<RwLock<Vec<StopConfig>>>,
+    // None = ei tallenneta (esim. testit)
+    path: Option<PathBuf>,
+    // Tiedoston lyhyiden tunnusten ("0802") tulkintaan
+    feed_id: String,
+    // Ilmoitus jokaisesta muutoksesta (esim. ajoneuvosyötteen aiheet)
+    changed: Arc<watch::Sender<()>>,
+}
+
+impl StopRegistry {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            stops: Arc::new(RwLock::new(settings.stops.clone())),
+            path: settings.path.clone(),
+            feed_id: settings.region.feed_id.clone(),
+            changed: Arc::new(watch::Sender::new(())),
+        }
+    }
+
+    pub fn in_memory(stops: Vec<StopConfig>) -> Self {
+        Self {
+            stops: Arc::new(RwLock::new(stops)),
+            path: None,
+            feed_id: String::new(),
+            changed: Arc::new(watch::Sender::new(())),
+        }
+    }
+
+    // Vastaanotin herää jokaisen onnistuneen muutoksen jälkeen
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.changed.subscribe()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    // Kaikki pysäkit järjestyksessä, myös piilotetut
+    pub fn snapshot(&self) -> Vec<StopConfig> {
+        self.stops.read().unwrap().clone()
+    }
+
+    pub fn visible(&self) -> Vec<StopConfig> {
+        self.stops.read().unwrap().iter().filter(|s| !s.hidden).cloned().collect()
+    }
+
+    pub fn get(&self, id: &StopId) -> Option<StopConfig> {
+        self.stops.read().unwrap().iter().find(|s| &s.id == id).cloned()
+    }
+
+    pub fn contains(&self, id: &StopId) -> bool {
+        self.stops.read().unwrap().iter().any(|s| &s.id == id)
+    }
+
+    // Lisää listan loppuun
+    pub fn add(&self, config: StopConfig) -> Result<(), RegistryError> {
+        self.modify(|stops| {
+            if stops.iter().any(|s| s.id == config.id) {
+                return Err(RegistryError::AlreadyTracked(config.id.clone()));
+            }
+            stops.push(config);
+            Ok(())
+        })
+    }
+
+    pub fn remove(&self, id: &StopId) -> Result<(), RegistryError> {
+        self.modify(|stops| {
+            let index = position(stops, id)?;
+            stops.remove(index);
+            Ok(())
+        })
+    }
+
+    pub fn update(&self, id: &StopId, edit: impl FnOnce(&mut StopConfig)) -> Result<(), RegistryError> {
+        self.modify(|stops| {
+            let index = position(stops, id)?;
+            edit(&mut stops[index]);
+            Ok(())
+        })
+    }
+
+    // Siirtää pysäkin annettuun kohtaan (indeksi rajataan listan pituuteen)
+    pub fn move_to(&self, id: &StopId, index: usize) -> Result<(), RegistryError> {
+        self.modify(|stops| {
+            let from = position(stops, id)?;
+            let config = stops.remove(from);
+            stops.insert(index.min(stops.len()), config);
+            Ok(())
+        })
+    }
+
+    // Siirtää pysäkin toisen eteen tai perään (vedä ja pudota)
+    pub fn move_beside(&self, id: &StopId, target: &StopId, after: bool) -> Result<(), RegistryError> {
+        if id == target {
+            return Ok(());
+        }
+        self.modify(|stops| {
+            let from = position(stops, id)?;
+            position(stops, target)?;
+            let config = stops.remove(from);
+            let to = position(stops, target)? + after as usize;
+            stops.insert(to, config);
+            Ok(())
+        })
+    }
+
+    // Muutos tehdään lukon alla ja tallennetaan heti; tallennusvirhe ei peru muutosta
+    fn modify(&self, change: impl FnOnce(&mut Vec<StopConfig>) -> Result<(), RegistryError>) -> Result<(), RegistryError> {
+        let mut stops = self.stops.write().unwrap();
+        change(&mut stops)?;
+        let saved = match &self.path {
+            Some(path) => save(path, &stops, &self.feed_id),
+            None => Ok(()),
+        };
+        drop(stops);
+        self.changed.send_replace(());
+        saved
+    }
+}
+
+fn position(stops: &[StopConfig], id: &StopId) -> Result<usize, RegistryError> {
+    stops.iter().position(|s| &s.id == id).ok_or_else(|| RegistryError::NotTracked(id.clone()))
+}
+
+// --- TALLENNUS ---
+
+pub fn save(path: &Path, stops: &[StopConfig], feed_id: &str) -> Result<(), RegistryError> {
+    let save_error = |error| RegistryError::Save { path: path.to_path_buf(), error };
+    let text = fs::read_to_string(path).map_err(save_error)?;
+    let mut doc: DocumentMut = text.parse().map_err(|e: toml_edit::TomlError| RegistryError::Parse {
+        path: path.to_path_buf(),
+        message: e.message().trim().to_string(),
+    })?;
+    write_stops(&mut doc, stops, feed_id);
+    write_atomic(path, &doc.to_string()).map_err(save_error)
+}
+
+// Päivittää [stops]-osion paikallaan: säilyttää muodon ([[stops]] tai vanha taulu),
+// olemassa olevien pysäkkien kommentit ja avaimet sekä asettaa järjestyksen listan mukaan.
+pub fn write_stops(doc: &mut DocumentMut, stops: &[StopConfig], feed_id: &str) {
+    let parse_id = |text: &str| StopId::parse(text, Some(feed_id)).ok();
+    // Tyhjä [[stops]]-lista katoaisi tiedostosta kokonaan
+    if stops.is_empty() && matches!(doc.get("stops"), None | Some(Item::ArrayOfTables(_))) {
+        doc.insert("stops", toml_edit::value(toml_edit::Array::new()));
+        return;
+    }
+    match doc.get_mut("stops") {
+        Some(Item::Table(table)) => {
+            table.retain(|key, _| parse_id(key).is_some_and(|id| stops.iter().any(|s| s.id == id)));
+            for config in stops {
+                let existing = table.iter().find(|(key, _)| parse_id(key).as_ref() == Some(&config.id)).map(|(key, _)| key.to_string());
+                match existing {
+                    Some(key) => write_entry(table.get_mut(&key).unwrap(), config),
+                    None => {
+                        table.insert(config.id.as_str(), Item::Value(entry_value(config)));
+                    }
+                }
+            }
+            let order = |key: &str| parse_id(key).and_then(|id| stops.iter().position(|s| s.id == id));
+            table.sort_values_by(|a, _, b, _| order(a).cmp(&order(b)));
+        }
+        Some(Item::ArrayOfTables(list)) => {
+            let mut old: Vec<Table> = list.iter().cloned().collect();
+            // Kaikki samaan kohtaan dokumentissa; vakaa lajittelu säilyttää listan järjestyksen
+            let anchor = old.iter().filter_map(Table::position).min();
+            let mut tables = ArrayOfTables::new();
+            for config in stops {
+                let found = old.iter().position(|t| {
+                    t.get("id").and_then(Item::as_str).and_then(parse_id).as_ref() == Some(&config.id)
+                });
+                let mut table = match found {
+                    Some(i) => old.remove(i),
+                    None => new_table(config),
+                };
+                write_fields(&mut table, config);
+                if let Some(anchor) = anchor {
+                    table.set_position(anchor);
+                }
+                tables.push(table);
+            }
+            *list = tables;
+        }
+        // Puuttuva tai muu muoto: kirjoitetaan uusi lista
+        _ => {
+            let mut tables = ArrayOfTables::new();
+            for config in stops {
+                let mut table = new_table(config);
+                write_fields(&mut table, config);
+                tables.push(table);
+            }
+            doc.insert("stops", Item::ArrayOfTables(tables));
+        }
+    }
+}
+
+fn new_table(config: &StopConfig) -> Table {
+    let mut table = Table::new();
+    table.decor_mut().set_prefix("\n");
+    table.insert("id", toml_edit::value(config.id.as_str()));
+    table
+}
+
+// Vanhan muodon arvo: pelkkä linjalista, jos muut kentät ovat oletuksia
+fn write_entry(item: &mut Item, config: &StopConfig) {
+    if let Some(table) = item.as_table_like_mut() {
+        write_fields(table, config);
+        return;
+    }
+    match item.as_value_mut() {
+        Some(value) => replace(value, entry_value(config)),
+        None => *item = Item::Value(entry_value(config)),
+    }
+}
+
+fn entry_value(config: &StopConfig) -> Value {
+    if is_plain(config) {
+        return lines_value(config);
+    }
+    let mut table = toml_edit::InlineTable::new();
+    write_fields(&mut table, config);
+    Value::InlineTable(table)
+}
+
+fn is_plain(config: &StopConfig) -> bool {
+    let defaults = StopConfig::new(config.id.clone(), Vec::new());
+    config.name.is_none()
+        && config.walk_minutes == 0
+        && !config.hidden
+        && !config.collapsed
+        && config.start_offset_minutes.is_none()
+        && config.lookahead_minutes == defaults.lookahead_minutes
+        && config.max_departures == defaults.max_departures
+        && config.per_line.is_none()
+}
+
+fn lines_value(config: &StopConfig) -> Value {
+    Value::Array(config.lines.iter().map(String::as_str).collect())
+}
+
+// Oletusarvoiset kentät kirjoitetaan vain, jos ne ovat jo tiedostossa
+fn write_fields(table: &mut dyn TableLike, config: &StopConfig) {
+    let defaults = StopConfig::new(config.id.clone(), Vec::new());
+    set_field(table, "lines", Some(lines_value(config)), false);
+    set_field(table, "name", config.name.as_deref().map(Value::from), false);
+    set_field(table, "walk_minutes", Some(int(config.walk_minutes)), config.walk_minutes == 0);
+    set_field(table, "hidden", Some(Value::from(config.hidden)), !config.hidden);
+    set_field(table, "collapsed", Some(Value::from(config.collapsed)), !config.collapsed);
+    set_field(table, "start_offset_minutes", config.start_offset_minutes.map(int), false);
+    set_field(table, "lookahead_minutes", Some(int(config.lookahead_minutes)), config.lookahead_minutes == defaults.lookahead_minutes);
+    set_field(table, "max_departures", Some(int(config.max_departures as u64)), config.max_departures == defaults.max_departures);
+    set_field(table, "per_line", config.per_line.map(|n| int(n as u64)), false);
+}
+
+fn int(n: u64) -> Value {
+    Value::from(n as i64)
+}
+
+// None poistaa avaimen. Muuttumatonta arvoa ei kosketa, muutettu saa vanhan arvon kommentit.
+fn set_field(table: &mut dyn TableLike, key: &str, value: Option<Value>, is_default: bool) {
+    let Some(value) = value else {
+        table.remove(key);
+        return;
+    };
+    match table.get_mut(key).and_then(Item::as_value_mut) {
+        Some(old) => replace(old, value),
+        None if is_default => {}
+        None => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+}
+
+fn replace(old: &mut Value, new: Value) {
+    if !same(old, &new) {
+        let decor = old.decor().clone();
+        *old = new;
+        *old.decor_mut() = decor;
+    }
+}
+
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| same(a, b)),
+        _ => false,
+    }
+}
+
+// Kirjoitetaan viereiseen väliaikaistiedostoon ja vaihdetaan paikalleen: keskeytynyt
+// tallennus ei jätä puolikasta asetustiedostoa
+fn write_atomic(path: &Path, text: &str) -> io::Result<()> {
+    let target = fs::canonicalize(path)?;
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = target.with_file_name(format!(".{}.tmp", name));
+    let result = write_and_replace(&tmp, &target, text);
+    // Epäonnistunut kirjoitus ei jätä väliaikaistiedostoa asetusten viereen
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn write_and_replace(tmp: &Path, target: &Path, text: &str) -> io::Result<()> {
+    {
+        let mut file = fs::File::create(tmp)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+    }
+    if let Ok(meta) = fs::metadata(target) {
+        let _ = fs::set_permissions(tmp, meta.permissions());
+    }
+    fs::rename(tmp, target)
+}
@@ -0,0 +1,501 @@
+use crate::keys;
+use crate::models::StopId;
+use crate::settings::{Overrides, Region, SETTINGS_VERSION};
+use std::fmt;
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, Key, TableLike, Value};
+
+// Asetustiedoston tarkistus: kaikki ongelmat kerralla, rivinumeron ja odotetun arvon kanssa.
+// serde pysähtyy ensimmäiseen virheeseen, joten kentät käydään läpi tämän skeeman avulla
+// ennen varsinaista lukua.
+
+// --- ONGELMAT ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigProblem {
+    pub severity: Severity,
+    // Avaimen polku, esim. "app.update_interval"
+    pub key: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.severity == Severity::Warning {
+            write!(f, "varoitus: ")?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "rivi {}: ", line)?;
+        }
+        if !self.key.is_empty() {
+            write!(f, "{}: ", self.key)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+// --- RAKENNE ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    // version 1: [api], [app], [stops] ...
+    Sectioned,
+    // Vanha muoto: api_key ja update_interval juuressa
+    Flat,
+}
+
+enum Expect {
+    Str,
+    Bool,
+    Int { min: i64 },
+    Float,
+    OneOf(&'static [&'static str]),
+    RegionPreset,
+    StrList,
+    KeyList,
+    Table(&'static [Field]),
+}
+
+struct Field {
+    key: &'static str,
+    expect: Expect,
+    required: bool,
+}
+
+const fn req(key: &'static str, expect: Expect) -> Field {
+    Field { key, expect, required: true }
+}
+
+const fn opt(key: &'static str, expect: Expect) -> Field {
+    Field { key, expect, required: false }
+}
+
+const API: &[Field] = &[req("api_key", Expect::Str)];
+
+const APP: &[Field] = &[
+    req("update_interval", Expect::Int { min: 1 }),
+    opt("fixture", Expect::Str),
+    opt("page_rotation_secs", Expect::Int { min: 0 }),
+];
+
+const FLAT_ROOT: &[Field] = &[
+    req("api_key", Expect::Str),
+    req("update_interval", Expect::Int { min: 1 }),
+];
+
+const STOP: &[Field] = &[
+    opt("lines", Expect::StrList),
+    opt("name", Expect::Str),
+    opt("walk_minutes", Expect::Int { min: 0 }),
+    opt("hidden", Expect::Bool),
+    opt("collapsed", Expect::Bool),
+    opt("start_offset_minutes", Expect::Int { min: 0 }),
+    opt("lookahead_minutes", Expect::Int { min: 1 }),
+    opt("max_departures", Expect::Int { min: 1 }),
+    opt("per_line", Expect::Int { min: 1 }),
+];
+
+const RETRY: &[Field] = &[
+    opt("timeout_secs", Expect::Int { min: 1 }),
+    opt("max_retries", Expect::Int { min: 0 }),
+    opt("backoff_base_ms", Expect::Int { min: 0 }),
+    opt("backoff_max_secs", Expect::Int { min: 0 }),
+];
+
+const VEHICLES: &[Field] = &[
+    opt("enabled", Expect::Bool),
+    opt("url", Expect::Str),
+    opt("format", Expect::OneOf(&["gtfsrt", "hfp"])),
+    opt("feed_id", Expect::Str),
+    opt("topic", Expect::Str),
+];
+
+const BOUNDARY: &[Field] = &[
+    req("min_lat", Expect::Float),
+    req("max_lat", Expect::Float),
+    req("min_lon", Expect::Float),
+    req("max_lon", Expect::Float),
+];
+
+const REGION: &[Field] = &[
+    opt("preset", Expect::RegionPreset),
+    opt("router", Expect::OneOf(&["waltti", "hsl", "finland"])),
+    opt("feed_id", Expect::Str),
+    opt("boundary", Expect::Table(BOUNDARY)),
+    opt("center_lat", Expect::Float),
+    opt("center_lon", Expect::Float),
+    opt("zoom", Expect::Float),
+];
+
+const LOGGING: &[Field] = &[
+    opt("level", Expect::Str),
+    opt("file", Expect::Bool),
+    opt("dir", Expect::Str),
+    opt("dump_api", Expect::Bool),
+];
+
+const KEYS: &[Field] = &[
+    opt("up", Expect::KeyList),
+    opt("down", Expect::KeyList),
+    opt("next_stop", Expect::KeyList),
+    opt("prev_stop", Expect::KeyList),
+    opt("open", Expect::KeyList),
+    opt("back", Expect::KeyList),
+    opt("refresh", Expect::KeyList),
+    opt("pause", Expect::KeyList),
+    opt("next_page", Expect::KeyList),
+    opt("prev_page", Expect::KeyList),
+    opt("help", Expect::KeyList),
+    opt("log", Expect::KeyList),
+    opt("quit", Expect::KeyList),
+];
+
+// Valinnaiset taulut, samat molemmissa muodoissa
+const OPTIONAL_SECTIONS: &[(&str, &[Field])] = &[
+    ("retry", RETRY),
+    ("vehicles", VEHICLES),
+    ("region", REGION),
+    ("logging", LOGGING),
+    ("keys", KEYS),
+];
+
+pub fn layout(root: &dyn TableLike) -> Layout {
+    let sectioned = ["version", "api", "app"].iter().any(|k| root.contains_key(k));
+    if !sectioned && (root.contains_key("api_key") || root.contains_key("update_interval")) {
+        Layout::Flat
+    } else {
+        Layout::Sectioned
+    }
+}
+
+// Pysäkkitunnusten oletussyöte: [region] feed_id tai valmiin alueen syöte.
+// `preset` = ympäristön alue (BUSSIVAHTI_REGION), joka korvaa tiedoston presetin.
+pub fn region_feed(root: &dyn TableLike, preset: Option<&str>) -> String {
+    let region = root.get("region").and_then(Item::as_table_like);
+    let field = |key: &str| region.and_then(|r| r.get(key)).and_then(Item::as_str);
+    match (field("feed_id"), preset.or(field("preset")).and_then(Region::preset)) {
+        (Some(feed), _) => feed.to_string(),
+        (None, Some(preset)) => preset.feed_id,
+        (None, None) => Region::default().feed_id,
+    }
+}
+
+// --- TARKISTUS ---
+
+pub fn check(text: &str) -> Vec<ConfigProblem> {
+    check_provided(text, &Overrides::default())
+}
+
+// `overrides`: ympäristömuuttujista tulevat arvot, esim. api.api_key. Niiden puuttuminen
+// tiedostosta ei ole virhe, ja ympäristön alue ratkaisee lyhyiden pysäkkitunnusten syötteen.
+pub fn check_provided(text: &str, overrides: &Overrides) -> Vec<ConfigProblem> {
+    let provided = overrides.keys();
+    let mut checker = Checker { text, provided: &provided, region_preset: overrides.get("region.preset"), problems: Vec::new() };
+    let doc = match ImDocument::parse(text) {
+        Ok(doc) => doc,
+        Err(e) => {
+            checker.error("", e.span(), format!("TOML-syntaksivirhe: {}", e.message().trim().replace('\n', "; ")));
+            return checker.problems;
+        }
+    };
+    let root = doc.as_table();
+
+    if let Some((key, item)) = root.get_key_value("version") {
+        match item.as_integer() {
+            Some(SETTINGS_VERSION) => {}
+            Some(v) => checker.error("version", key.span(), format!("tuntematon versio {} (tuettu: {})", v, SETTINGS_VERSION)),
+            None => checker.mismatch("version", key, item, "kokonaisluku"),
+        }
+    }
+
+    let mut known: Vec<&str> = vec!["version", "fixture", "stops"];
+    match layout(root) {
+        Layout::Sectioned => {
+            checker.section(root, "api", API, true);
+            checker.section(root, "app", APP, true);
+            known.extend(["api", "app"]);
+        }
+        Layout::Flat => {
+            checker.problems.push(ConfigProblem {
+                severity: Severity::Warning,
+                key: String::new(),
+                line: None,
+                message: "vanha litteä asetusmuoto; siirrä api_key [api]-tauluun ja update_interval [app]-tauluun (ks. Settings.sample.toml)".to_string(),
+            });
+            checker.required_fields("", root, FLAT_ROOT, None);
+            known.extend(["api_key", "update_interval"]);
+        }
+    }
+    if let Some((key, item)) = root.get_key_value("fixture") {
+        checker.value("fixture", key, item, &Expect::Str);
+    }
+
+    checker.stops(root);
+    for (name, fields) in OPTIONAL_SECTIONS {
+        checker.section(root, name, fields, false);
+        known.push(name);
+    }
+    checker.key_conflicts(root);
+    checker.unknown_keys("", root, &known);
+
+    checker.problems.sort_by_key(|p| (p.line.unwrap_or(0), std::cmp::Reverse(p.severity)));
+    checker.problems
+}
+
+// Listamuodon pysäkkitaulu ja sen sijainti tiedostossa
+type ListedStop<'a> = (&'a dyn TableLike, Option<Range<usize>>);
+
+struct Checker<'a> {
+    text: &'a str,
+    provided: &'a [&'a str],
+    region_preset: Option<&'a str>,
+    problems: Vec<ConfigProblem>,
+}
+
+impl Checker<'_> {
+    // Litteän muodon juuriavaimet vastaavat [api]/[app]-taulujen avaimia
+    fn is_provided(&self, path: &str) -> bool {
+        self.provided.iter().any(|p| *p == path || (!path.contains('.') && p.rsplit('.').next() == Some(path)))
+    }
+
+    fn line(&self, span: Option<Range<usize>>) -> Option<usize> {
+        span.map(|s| line_at(self.text, s.start))
+    }
+
+    fn push(&mut self, severity: Severity, key: &str, span: Option<Range<usize>>, message: String) {
+        let line = self.line(span);
+        self.problems.push(ConfigProblem { severity, key: key.to_string(), line, message });
+    }
+
+    fn error(&mut self, key: &str, span: Option<Range<usize>>, message: String) {
+        self.push(Severity::Error, key, span, message);
+    }
+
+    fn mismatch(&mut self, path: &str, key: &Key, item: &Item, expected: &str) {
+        self.error(path, key.span(), format!("odotettiin {}, saatiin {}", expected, type_name(item)));
+    }
+
+    fn section(&mut self, root: &dyn TableLike, name: &str, fields: &[Field], required: bool) {
+        match root.get_key_value(name) {
+            Some((key, item)) => match item.as_table_like() {
+                Some(table) => self.fields(name, table, fields, key.span()),
+                None => self.mismatch(name, key, item, "taulua"),
+            },
+            None if required && fields.iter().any(|f| f.required && !self.is_provided(&join(name, f.key))) => self.error(name, None, format!("puuttuu pakollinen taulu [{}]", name)),
+            None => {}
+        }
+    }
+
+    fn fields(&mut self, prefix: &str, table: &dyn TableLike, fields: &[Field], header: Option<Range<usize>>) {
+        self.required_fields(prefix, table, fields, header);
+        let known: Vec<&str> = fields.iter().map(|f| f.key).collect();
+        self.unknown_keys(prefix, table, &known);
+    }
+
+    // Kuten fields, mutta ei varoita muista avaimista (litteän muodon juuri)
+    fn required_fields(&mut self, prefix: &str, table: &dyn TableLike, fields: &[Field], header: Option<Range<usize>>) {
+        for field in fields {
+            let path = join(prefix, field.key);
+            match table.get_key_value(field.key) {
+                Some((key, item)) => self.value(&path, key, item, &field.expect),
+                None if field.required && !self.is_provided(&path) => self.error(&path, header.clone(), "puuttuu pakollinen avain".to_string()),
+                None => {}
+            }
+        }
+    }
+
+    fn unknown_keys(&mut self, prefix: &str, table: &dyn TableLike, known: &[&str]) {
+        for (name, _) in table.iter() {
+            if !known.contains(&name) {
+                let span = table.get_key_value(name).and_then(|(k, _)| k.span());
+                self.push(Severity::Warning, &join(prefix, name), span, "tuntematon avain, ohitetaan".to_string());
+            }
+        }
+    }
+
+    fn value(&mut self, path: &str, key: &Key, item: &Item, expect: &Expect) {
+        match expect {
+            Expect::Str if item.is_str() => {}
+            Expect::Str => self.mismatch(path, key, item, "merkkijonoa"),
+            Expect::Bool if item.is_bool() => {}
+            Expect::Bool => self.mismatch(path, key, item, "totuusarvoa (true/false)"),
+            Expect::Int { min } => match item.as_integer() {
+                Some(v) if v >= *min => {}
+                Some(v) => self.error(path, key.span(), format!("odotettiin kokonaislukua ≥ {}, saatiin {}", min, v)),
+                None => self.mismatch(path, key, item, &format!("kokonaislukua ≥ {}", min)),
+            },
+            Expect::Float if item.is_float() || item.is_integer() => {}
+            Expect::Float => self.mismatch(path, key, item, "numeroa"),
+            Expect::OneOf(options) => match item.as_str() {
+                Some(s) if options.contains(&s) => {}
+                Some(s) => self.error(path, key.span(), format!("odotettiin yhtä arvoista {}, saatiin \"{}\"", options.join(", "), s)),
+                None => self.mismatch(path, key, item, &format!("yhtä arvoista {}", options.join(", "))),
+            },
+            Expect::RegionPreset => match item.as_str() {
+                Some(s) if Region::preset(s).is_some() => {}
+                Some(s) => self.error(path, key.span(), format!(
+                    "tuntematon alue \"{}\" (vaihtoehdot: {})", s, Region::preset_names().join(", ")
+                )),
+                None => self.mismatch(path, key, item, "alueen nimeä"),
+            },
+            Expect::StrList => match item.as_array() {
+                Some(list) if list.iter().all(Value::is_str) => {}
+                Some(_) => self.error(path, key.span(), "odotettiin listaa merkkijonoja, esim. [\"1\", \"3\"]".to_string()),
+                None => self.mismatch(path, key, item, "listaa merkkijonoja"),
+            },
+            Expect::KeyList => match item.as_array() {
+                Some(list) => {
+                    for value in list.iter() {
+                        match value.as_str().map(str::parse::<keys::Key>) {
+                            Some(Ok(_)) => {}
+                            Some(Err(e)) => self.error(path, value.span(), e),
+                            None => self.error(path, key.span(), "odotettiin listaa näppäimiä, esim. [\"r\", \"F5\"]".to_string()),
+                        }
+                    }
+                }
+                None => self.mismatch(path, key, item, "listaa näppäimiä"),
+            },
+            Expect::Table(fields) => match item.as_table_like() {
+                Some(table) => self.fields(path, table, fields, key.span()),
+                None => self.mismatch(path, key, item, "taulua"),
+            },
+        }
+    }
+
+    // Sama näppäin kahdelle toiminnolle: vain ensimmäinen toimii. Asettamattomat toiminnot pitävät oletuksensa.
+    fn key_conflicts(&mut self, root: &dyn TableLike) {
+        let Some(table) = root.get("keys").and_then(Item::as_table_like) else { return };
+        let defaults = keys::KeyBindings::default();
+        let mut bound: Vec<(keys::Key, keys::Action)> = keys::Action::ALL.into_iter()
+            .filter(|a| !table.contains_key(a.key()))
+            .flat_map(|a| defaults.keys(a).iter().map(move |k| (*k, a)))
+            .collect();
+        for action in keys::Action::ALL {
+            let Some(list) = table.get(action.key()).and_then(Item::as_array) else { continue };
+            for value in list.iter() {
+                let Some(Ok(key)) = value.as_str().map(str::parse::<keys::Key>) else { continue };
+                match bound.iter().find(|(k, a)| *k == key && *a != action) {
+                    Some((_, other)) => self.push(
+                        Severity::Warning,
+                        &join("keys", action.key()),
+                        value.span(),
+                        format!("näppäin \"{}\" on jo sidottu toimintoon {}", key, other.key()),
+                    ),
+                    None => bound.push((key, action)),
+                }
+            }
+        }
+    }
+
+    fn stops(&mut self, root: &dyn TableLike) {
+        let Some((key, item)) = root.get_key_value("stops") else {
+            self.error("stops", None, "puuttuu pakollinen taulu [[stops]]".to_string());
+            return;
+        };
+        let feed = region_feed(root, self.region_preset);
+        let mut seen: Vec<(StopId, Option<usize>)> = Vec::new();
+
+        // Listamuoto: [[stops]] tai stops = [{ id = ... }, ...]
+        let listed: Option<Vec<ListedStop>> = match item {
+            Item::ArrayOfTables(tables) => Some(tables.iter().map(|t| (t as &dyn TableLike, t.span())).collect()),
+            Item::Value(Value::Array(list)) => list
+                .iter()
+                .map(|v| v.as_inline_table().map(|t| (t as &dyn TableLike, t.span())))
+                .collect(),
+            _ => None,
+        };
+        if let Some(listed) = listed {
+            for (i, (table, span)) in listed.into_iter().enumerate() {
+                let path = format!("stops[{}]", i);
+                match table.get_key_value("id") {
+                    Some((id_key, id_item)) => match id_item.as_str() {
+                        Some(id) => self.stop_id(&path, id, &feed, id_key.span(), &mut seen),
+                        None => self.mismatch(&format!("{}.id", path), id_key, id_item, "merkkijonoa"),
+                    },
+                    None => self.error(&format!("{}.id", path), span.clone(), "puuttuu pakollinen avain".to_string()),
+                }
+                self.required_fields(&path, table, STOP, span);
+                let mut known: Vec<&str> = STOP.iter().map(|f| f.key).collect();
+                known.push("id");
+                self.unknown_keys(&path, table, &known);
+            }
+            return;
+        }
+        if item.is_array() {
+            self.error("stops", key.span(), "odotettiin listaa tauluja { id = \"...\", lines = [...] }".to_string());
+            return;
+        }
+
+        // Vanha muoto: tunnus avaimena
+        let Some(stops) = item.as_table_like() else {
+            self.mismatch("stops", key, item, "[[stops]]-listaa tai taulua");
+            return;
+        };
+        for (id, _) in stops.iter() {
+            let Some((key, entry)) = stops.get_key_value(id) else { continue };
+            let path = format!("stops.\"{}\"", id);
+            self.stop_id(&path, id, &feed, key.span(), &mut seen);
+            if entry.is_array() {
+                self.value(&path, key, entry, &Expect::StrList);
+            } else if let Some(table) = entry.as_table_like() {
+                self.fields(&path, table, STOP, key.span());
+            } else {
+                self.mismatch(&path, key, entry, "linjalistaa tai taulua { lines = [...] }");
+            }
+        }
+    }
+
+    fn stop_id(&mut self, path: &str, id: &str, feed: &str, span: Option<Range<usize>>, seen: &mut Vec<(StopId, Option<usize>)>) {
+        let line = self.line(span.clone());
+        match StopId::parse(id, Some(feed)) {
+            Ok(stop_id) => match seen.iter().find(|(s, _)| *s == stop_id) {
+                Some((_, first)) => {
+                    let at = first.map(|l| format!(" rivillä {}", l)).unwrap_or_default();
+                    self.push(Severity::Warning, path, span, format!("pysäkki {} on jo listassa{}, ohitetaan", stop_id, at));
+                }
+                None => seen.push((stop_id, line)),
+            },
+            Err(e) => self.error(path, span, e.to_string()),
+        }
+    }
+}
+
+// Tavusijainnin rivinumero (1-pohjainen)
+pub fn line_at(text: &str, offset: usize) -> usize {
+    text.as_bytes()[..offset.min(text.len())].iter().filter(|&&b| b == b'\n').count() + 1
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn type_name(item: &Item) -> &'static str {
+    match item {
+        Item::None => "tyhjä",
+        Item::Value(Value::String(_)) => "merkkijono",
+        Item::Value(Value::Integer(_)) => "kokonaisluku",
+        Item::Value(Value::Float(_)) => "liukuluku",
+        Item::Value(Value::Boolean(_)) => "totuusarvo",
+        Item::Value(Value::Datetime(_)) => "aikaleima",
+        Item::Value(Value::Array(_)) => "lista",
+        Item::Value(Value::InlineTable(_)) | Item::Table(_) => "taulu",
+        Item::ArrayOfTables(_) => "taulukkolista",
+    }
+}
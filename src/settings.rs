@@ -1,21 +1,675 @@
-use config::{Config, ConfigError, File};
+use crate::keys::KeyBindings;
+use crate::models::StopId;
+use crate::schema::{self, ConfigProblem, Severity};
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize, Clone)]
+// Asetustiedoston nimi käyttäjän asetuskansiossa ja työhakemistossa
+pub const SETTINGS_FILE: &str = "Settings.toml";
+// Asetustiedoston muodon versio (avain `version`)
+pub const SETTINGS_VERSION: i64 = 1;
+// Asetustiedoston polku ympäristömuuttujasta (--config ohittaa)
+pub const CONFIG_ENV: &str = "BUSSIVAHTI_CONFIG";
+
+// Ympäristömuuttujat, jotka ohittavat yksittäisen avaimen tiedostosta: (muuttuja, avain)
+pub const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("BUSSIVAHTI_API_KEY", "api.api_key"),
+    ("BUSSIVAHTI_UPDATE_INTERVAL", "app.update_interval"),
+    ("BUSSIVAHTI_FIXTURE", "app.fixture"),
+    ("BUSSIVAHTI_REGION", "region.preset"),
+    ("BUSSIVAHTI_LOG_LEVEL", "logging.level"),
+];
+
+#[derive(Debug, Clone)]
 pub struct Settings {
     pub api_key: String,
     pub update_interval: u64,
-    pub stops: HashMap<String, Vec<String>>,
+    // Näyttöjärjestyksessä
+    pub stops: Vec<StopConfig>,
+    // Jos asetettu, lähdöt luetaan tästä JSON-tiedostosta rajapinnan sijaan (demo / offline)
+    pub fixture: Option<String>,
+    // Lähtötaulun sivujen automaattinen vaihto sekunteina (valvomattomat näytöt); None = pois
+    pub page_rotation_secs: Option<u64>,
+    pub retry: RetryPolicy,
+    pub vehicles: VehicleSettings,
+    pub region: Region,
+    pub logging: LogSettings,
+    // Lähtötaulun näppäimet ([keys])
+    pub keys: KeyBindings,
+    // Latauksessa havaitut varoitukset (vanha muoto, tuntemattomat avaimet); kirjataan lokiin
+    pub warnings: Vec<ConfigProblem>,
+    // Tiedosto, josta asetukset luettiin
+    pub path: Option<PathBuf>,
+}
+
+// Pysäkin asetukset. Pysäkit ovat järjestetty lista, jossa jokainen on oma taulunsa:
+//   [[stops]]
+//   id = "tampere:0802"
+//   lines = ["1"]
+//   name = "Kotipysäkki"
+//   walk_minutes = 4
+// Vanha muoto (taulu, avaimena tunnus) toimii edelleen, järjestys säilyy tiedoston mukaisena:
+//   [stops]
+//   "tampere:0802" = ["1"]
+//   "tampere:0001" = { lines = ["3"], lookahead_minutes = 60, max_departures = 5, per_line = 2 }
+#[derive(Debug, Clone)]
+pub struct StopConfig {
+    pub id: StopId,
+    pub lines: Vec<String>,
+    // Oma näyttönimi; oletuksena pysäkin nimi rajapinnasta
+    pub name: Option<String>,
+    // Kävelyaika pysäkille minuutteina
+    pub walk_minutes: u64,
+    // Piilotettu pysäkki pysyy asetuksissa, mutta sitä ei haeta eikä näytetä
+    pub hidden: bool,
+    // Pienennetty paneeli: vain otsikko ja seuraavat lähdöt yhdellä rivillä
+    pub collapsed: bool,
+    // Näytetään vain lähdöt, joihin on vähintään näin monta minuuttia. None = walk_minutes.
+    pub start_offset_minutes: Option<u64>,
+    // Kuinka pitkälle eteenpäin lähtöjä haetaan
+    pub lookahead_minutes: u64,
+    // Näytettävien lähtöjen kokonaismäärä
+    pub max_departures: usize,
+    // "Seuraavat N per linja" -tila
+    pub per_line: Option<usize>,
+}
+
+impl StopConfig {
+    pub fn new(id: StopId, lines: Vec<String>) -> Self {
+        Self {
+            id,
+            lines,
+            name: None,
+            walk_minutes: 0,
+            hidden: false,
+            collapsed: false,
+            start_offset_minutes: None,
+            lookahead_minutes: 24 * 60,
+            max_departures: 5,
+            per_line: None,
+        }
+    }
+
+    // Hakuikkunan alku: oma arvo tai kävelyaika
+    pub fn start_offset(&self) -> u64 {
+        self.start_offset_minutes.unwrap_or(self.walk_minutes)
+    }
+
+    pub fn accepts_line(&self, line: &str) -> bool {
+        self.lines.iter().any(|l| l == "ALL" || l == line)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StopEntry {
+    Lines(Vec<String>),
+    Detailed(StopFields),
+}
+
+#[derive(Deserialize)]
+struct StopFields {
+    // Vain listamuodossa; vanhassa muodossa tunnus on avain
+    id: Option<String>,
+    #[serde(default = "all_lines")]
+    lines: Vec<String>,
+    name: Option<String>,
+    walk_minutes: Option<u64>,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    collapsed: bool,
+    start_offset_minutes: Option<u64>,
+    lookahead_minutes: Option<u64>,
+    max_departures: Option<usize>,
+    per_line: Option<usize>,
+}
+
+fn all_lines() -> Vec<String> {
+    vec!["ALL".to_string()]
+}
+
+impl StopEntry {
+    fn into_config(self, id: StopId) -> StopConfig {
+        match self {
+            StopEntry::Lines(lines) => StopConfig::new(id, lines),
+            StopEntry::Detailed(f) => {
+                let defaults = StopConfig::new(id, f.lines);
+                StopConfig {
+                    name: f.name,
+                    walk_minutes: f.walk_minutes.unwrap_or(0),
+                    hidden: f.hidden,
+                    collapsed: f.collapsed,
+                    start_offset_minutes: f.start_offset_minutes,
+                    lookahead_minutes: f.lookahead_minutes.unwrap_or(defaults.lookahead_minutes),
+                    max_departures: f.max_departures.unwrap_or(defaults.max_departures),
+                    per_line: f.per_line,
+                    ..defaults
+                }
+            }
+        }
+    }
+}
+
+// [stops] sellaisenaan: (tunnus, asetukset) tiedoston järjestyksessä, kumpi muoto tahansa
+struct RawStops(Vec<(String, StopEntry)>);
+
+impl<'de> Deserialize<'de> for RawStops {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StopsVisitor;
+
+        impl<'de> Visitor<'de> for StopsVisitor {
+            type Value = RawStops;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("[[stops]]-lista tai [stops]-taulu")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<RawStops, A::Error> {
+                let mut stops = Vec::new();
+                while let Some(fields) = seq.next_element::<StopFields>()? {
+                    let id = fields.id.clone().ok_or_else(|| de::Error::missing_field("id"))?;
+                    stops.push((id, StopEntry::Detailed(fields)));
+                }
+                Ok(RawStops(stops))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<RawStops, A::Error> {
+                let mut stops = Vec::new();
+                while let Some(entry) = map.next_entry::<String, StopEntry>()? {
+                    stops.push(entry);
+                }
+                Ok(RawStops(stops))
+            }
+        }
+
+        deserializer.deserialize_any(StopsVisitor)
+    }
+}
+
+// [retry]-taulu: pyyntöjen aikakatkaisu ja uudelleenyritykset
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Yksittäisen HTTP-pyynnön aikakatkaisu (sekunteina)
+    pub timeout_secs: u64,
+    // Montako kertaa epäonnistunut pyyntö yritetään uudelleen
+    pub max_retries: u32,
+    // Ensimmäisen uusintayrityksen odotus; tuplaantuu joka kerta
+    pub backoff_base_ms: u64,
+    // Oman odotuksen yläraja, koskee myös koko päivityssilmukkaa. Palvelimen
+    // Retry-After noudatetaan sellaisenaan.
+    pub backoff_max_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 10,
+            max_retries: 2,
+            backoff_base_ms: 500,
+            backoff_max_secs: 300,
+        }
+    }
+}
+
+impl Settings {
+    // Etsii asetustiedoston (ks. find_config) ja lukee sen ympäristömuuttujilla täydennettynä
+    pub fn new(explicit: Option<&Path>) -> Result<Self, SettingsError> {
+        Self::load(&find_config(explicit)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SettingsError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| SettingsError::Io { path: path.to_path_buf(), error })?;
+        let mut settings = Self::parse_with(&text, &Overrides::from_env())
+            .map_err(|problems| SettingsError::Invalid { path: path.to_path_buf(), problems })?;
+        settings.path = Some(path.to_path_buf());
+        Ok(settings)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Vec<ConfigProblem>> {
+        Self::parse_with(text, &Overrides::default())
+    }
+
+    // Virheet palautetaan kaikki kerralla; pelkät varoitukset eivät estä latausta
+    pub fn parse_with(text: &str, overrides: &Overrides) -> Result<Self, Vec<ConfigProblem>> {
+        let mut problems = schema::check_provided(text, overrides);
+        problems.extend(overrides.problems());
+        if problems.iter().any(ConfigProblem::is_error) {
+            return Err(problems);
+        }
+
+        let raw: SettingsFile = toml_edit::de::from_str(text).map_err(|e| {
+            vec![ConfigProblem {
+                severity: Severity::Error,
+                key: String::new(),
+                line: e.span().map(|s| schema::line_at(text, s.start)),
+                message: e.message().trim().to_string(),
+            }]
+        })?;
+
+        // Vanha litteä muoto pitää avaimet juuressa; skeema on jo varmistanut, että ne löytyvät jostain
+        let (file_key, file_interval, app_fixture, page_rotation_secs) = match raw.app {
+            Some(app) => (raw.api.and_then(|a| a.api_key), app.update_interval, app.fixture, app.page_rotation_secs),
+            None => (raw.api.and_then(|a| a.api_key).or(raw.api_key), raw.update_interval, None, None),
+        };
+        let api_key = overrides.get("api.api_key").map(str::to_string).or(file_key).unwrap_or_default();
+        let update_interval = overrides.get("app.update_interval").and_then(|v| v.parse().ok())
+            .or(file_interval)
+            .unwrap_or_default();
+        let fixture = overrides.get("app.fixture").map(str::to_string).or(app_fixture).or(raw.fixture);
+
+        // BUSSIVAHTI_REGION vaihtaa pohja-alueen; tiedoston omat [region]-kentät pätevät sen päällä
+        let mut region_entry = raw.region;
+        if let Some(name) = overrides.get("region.preset") {
+            region_entry.preset = Some(name.to_string());
+        }
+        let region = Region::try_from(region_entry).map_err(|message| {
+            vec![ConfigProblem { severity: Severity::Error, key: "region.preset".to_string(), line: None, message }]
+        })?;
+        let mut stops: Vec<StopConfig> = Vec::new();
+        for (key, entry) in raw.stops.0 {
+            // Pelkkä pysäkkikoodi saa alueen syötteen etuliitteen
+            let id = StopId::parse(&key, Some(&region.feed_id)).map_err(|e| {
+                vec![ConfigProblem { severity: Severity::Error, key: format!("stops.\"{}\"", key), line: None, message: e.to_string() }]
+            })?;
+            if !stops.iter().any(|s| s.id == id) {
+                stops.push(entry.into_config(id));
+            }
+        }
+
+        let mut vehicles = raw.vehicles;
+        if vehicles.feed_id.is_empty() {
+            vehicles.feed_id = region.feed_id.clone();
+        }
+        let mut logging = raw.logging;
+        if let Some(level) = overrides.get("logging.level") {
+            logging.level = level.to_string();
+        }
+
+        Ok(Settings {
+            api_key,
+            update_interval,
+            stops,
+            fixture,
+            page_rotation_secs: page_rotation_secs.filter(|s| *s > 0),
+            retry: raw.retry,
+            vehicles,
+            region,
+            logging,
+            keys: raw.keys,
+            warnings: problems,
+            path: None,
+        })
+    }
 }
 
 impl Settings {
-    pub fn new() -> Result<Self, ConfigError> {
-        let s = Config::builder()
-            // Etsii tiedostoa nimeltä "Settings" projektin juuresta
-            .add_source(File::with_name("Settings"))
-            .build()?;
+    pub fn stop(&self, id: &StopId) -> Option<&StopConfig> {
+        self.stops.iter().find(|s| &s.id == id)
+    }
+
+    // Haettavat ja näytettävät pysäkit järjestyksessä
+    pub fn visible_stops(&self) -> impl Iterator<Item = &StopConfig> {
+        self.stops.iter().filter(|s| !s.hidden)
+    }
+}
+
+// Hakujärjestys: --config, $BUSSIVAHTI_CONFIG, käyttäjän asetuskansio
+// (esim. ~/.config/bussivahti/Settings.toml), työhakemisto.
+// Erikseen annetun polun on oltava olemassa; muista käytetään ensimmäistä löytyvää.
+pub fn find_config(explicit: Option<&Path>) -> Result<PathBuf, SettingsError> {
+    if let Some(path) = explicit {
+        return Ok(path.to_path_buf());
+    }
+    if let Some(path) = std::env::var_os(CONFIG_ENV).filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    let candidates: Vec<PathBuf> = config_dir()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .into_iter()
+        .chain([PathBuf::from(SETTINGS_FILE)])
+        .collect();
+    match candidates.iter().find(|p| p.is_file()) {
+        Some(path) => Ok(path.clone()),
+        None => Err(SettingsError::NotFound { searched: candidates }),
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("bussivahti"))
+}
+
+// `--config <polku>` tai `--config=<polku>` komentoriviltä binääreille, joilla ei ole clapia
+pub fn config_arg() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+// Ympäristömuuttujista luetut ohitukset, ks. ENV_OVERRIDES
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    // (muuttuja, avain, arvo)
+    values: Vec<(&'static str, &'static str, String)>,
+}
 
-        s.try_deserialize()
+impl Overrides {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|var| std::env::var(var).ok())
     }
-}
\ No newline at end of file
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let values = ENV_OVERRIDES
+            .iter()
+            .filter_map(|(var, key)| lookup(var).filter(|v| !v.is_empty()).map(|v| (*var, *key, v)))
+            .collect();
+        Self { values }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.iter().find(|(_, k, _)| *k == key).map(|(_, _, v)| v.as_str())
+    }
+
+    pub fn keys(&self) -> Vec<&'static str> {
+        self.values.iter().map(|(_, key, _)| *key).collect()
+    }
+
+    // Käytössä olevat muuttujat, esim. "BUSSIVAHTI_API_KEY -> api.api_key"
+    pub fn describe(&self) -> Vec<String> {
+        self.values.iter().map(|(var, key, _)| format!("{} -> {}", var, key)).collect()
+    }
+
+    pub fn problems(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        for (var, key, value) in &self.values {
+            let message = match *key {
+                "app.update_interval" if !value.parse::<u64>().is_ok_and(|v| v >= 1) => {
+                    format!("odotettiin kokonaislukua ≥ 1, saatiin \"{}\"", value)
+                }
+                "region.preset" if Region::preset(value).is_none() => {
+                    format!("tuntematon alue \"{}\" (vaihtoehdot: {})", value, Region::preset_names().join(", "))
+                }
+                _ => continue,
+            };
+            problems.push(ConfigProblem { severity: Severity::Error, key: var.to_string(), line: None, message });
+        }
+        problems
+    }
+}
+
+// Tiedoston rakenne sellaisenaan: sekä [api]/[app]-muoto että vanha litteä muoto
+#[derive(Deserialize)]
+struct SettingsFile {
+    api: Option<ApiSection>,
+    app: Option<AppSection>,
+    api_key: Option<String>,
+    update_interval: Option<u64>,
+    fixture: Option<String>,
+    stops: RawStops,
+    #[serde(default)]
+    retry: RetryPolicy,
+    #[serde(default)]
+    vehicles: VehicleSettings,
+    // Luetaan kenttinä, jotta ympäristön alue voi korvata pelkän presetin
+    #[serde(default)]
+    region: RegionEntry,
+    #[serde(default)]
+    logging: LogSettings,
+    #[serde(default)]
+    keys: KeyBindings,
+}
+
+#[derive(Deserialize)]
+struct ApiSection {
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AppSection {
+    update_interval: Option<u64>,
+    fixture: Option<String>,
+    page_rotation_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    NotFound { searched: Vec<PathBuf> },
+    Io { path: PathBuf, error: std::io::Error },
+    Invalid { path: PathBuf, problems: Vec<ConfigProblem> },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::NotFound { searched } => {
+                write!(f, "Asetustiedostoa ei löytynyt. Haettiin:")?;
+                for path in searched {
+                    write!(f, "\n  {}", path.display())?;
+                }
+                write!(f, "\nKopioi Settings.sample.toml johonkin näistä, anna polku valitsimella --config tai ympäristömuuttujassa {}.", CONFIG_ENV)
+            }
+            SettingsError::Io { path, error } => write!(f, "Asetustiedostoa {} ei voitu lukea: {}", path.display(), error),
+            SettingsError::Invalid { path, problems } => {
+                write!(f, "Virheelliset asetukset tiedostossa {}:", path.display())?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+// [region]-taulu: reititin, hakualue ja kartan oletusnäkymä.
+// Valmis alue valitaan nimellä, yksittäiset kentät ohittavat sen arvot:
+//   [region]
+//   preset = "turku"
+//   zoom = 14
+#[derive(Debug, Deserialize, Clone)]
+#[serde(try_from = "RegionEntry")]
+pub struct Region {
+    pub name: String,
+    pub router: Router,
+    // Pysäkkitunnusten etuliite, esim. "tampere" -> "tampere:0802"
+    pub feed_id: String,
+    pub boundary: BoundingBox,
+    pub center_lat: f64,
+    pub center_lon: f64,
+    pub zoom: f64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Router {
+    Waltti,
+    Hsl,
+    Finland,
+}
+
+impl Router {
+    pub fn api_url(self) -> &'static str {
+        match self {
+            Router::Waltti => "https://api.digitransit.fi/routing/v2/waltti/gtfs/v1",
+            Router::Hsl => "https://api.digitransit.fi/routing/v2/hsl/gtfs/v1",
+            Router::Finland => "https://api.digitransit.fi/routing/v2/finland/gtfs/v1",
+        }
+    }
+}
+
+// Geokoodauksen hakualue (boundary.rect)
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+struct Preset {
+    name: &'static str,
+    router: Router,
+    feed_id: &'static str,
+    // (lat, lon)
+    center: (f64, f64),
+    // (min_lat, max_lat, min_lon, max_lon)
+    boundary: (f64, f64, f64, f64),
+    zoom: f64,
+}
+
+const PRESETS: &[Preset] = &[
+    Preset { name: "tampere", router: Router::Waltti, feed_id: "tampere", center: (61.498, 23.76), boundary: (61.4, 61.6, 23.5, 24.0), zoom: 16.0 },
+    Preset { name: "turku", router: Router::Waltti, feed_id: "FOLI", center: (60.4518, 22.2666), boundary: (60.35, 60.6, 22.0, 22.6), zoom: 16.0 },
+    Preset { name: "oulu", router: Router::Waltti, feed_id: "OULU", center: (65.0121, 25.4651), boundary: (64.85, 65.2, 25.2, 25.9), zoom: 16.0 },
+    Preset { name: "jyvaskyla", router: Router::Waltti, feed_id: "LINKKI", center: (62.2426, 25.7473), boundary: (62.1, 62.4, 25.5, 26.0), zoom: 16.0 },
+    Preset { name: "kuopio", router: Router::Waltti, feed_id: "Kuopio", center: (62.8924, 27.677), boundary: (62.75, 63.05, 27.4, 28.0), zoom: 16.0 },
+    Preset { name: "lahti", router: Router::Waltti, feed_id: "LSL", center: (60.9827, 25.6615), boundary: (60.85, 61.1, 25.4, 25.9), zoom: 16.0 },
+    Preset { name: "joensuu", router: Router::Waltti, feed_id: "JOENSUU", center: (62.601, 29.7636), boundary: (62.5, 62.7, 29.5, 30.0), zoom: 16.0 },
+    Preset { name: "hameenlinna", router: Router::Waltti, feed_id: "Hameenlinna", center: (60.9959, 24.4643), boundary: (60.9, 61.1, 24.2, 24.7), zoom: 16.0 },
+    Preset { name: "lappeenranta", router: Router::Waltti, feed_id: "Lappeenranta", center: (61.0587, 28.1887), boundary: (60.95, 61.15, 27.9, 28.5), zoom: 16.0 },
+    Preset { name: "vaasa", router: Router::Waltti, feed_id: "Vaasa", center: (63.0951, 21.6165), boundary: (63.0, 63.2, 21.4, 21.9), zoom: 16.0 },
+    Preset { name: "rovaniemi", router: Router::Waltti, feed_id: "Rovaniemi", center: (66.5039, 25.7294), boundary: (66.4, 66.6, 25.5, 26.0), zoom: 16.0 },
+    Preset { name: "kotka", router: Router::Waltti, feed_id: "Kotka", center: (60.4664, 26.9458), boundary: (60.4, 60.6, 26.7, 27.2), zoom: 16.0 },
+    Preset { name: "mikkeli", router: Router::Waltti, feed_id: "Mikkeli", center: (61.6886, 27.2723), boundary: (61.6, 61.8, 27.0, 27.5), zoom: 16.0 },
+    Preset { name: "kouvola", router: Router::Waltti, feed_id: "Kouvola", center: (60.8679, 26.7042), boundary: (60.75, 61.0, 26.4, 27.0), zoom: 16.0 },
+    Preset { name: "kajaani", router: Router::Waltti, feed_id: "Kajaani", center: (64.227, 27.7285), boundary: (64.1, 64.35, 27.5, 28.0), zoom: 16.0 },
+    Preset { name: "salo", router: Router::Waltti, feed_id: "Salo", center: (60.3833, 23.1333), boundary: (60.3, 60.5, 22.9, 23.4), zoom: 16.0 },
+    Preset { name: "pori", router: Router::Waltti, feed_id: "Pori", center: (61.4851, 21.7974), boundary: (61.4, 61.6, 21.5, 22.0), zoom: 16.0 },
+    Preset { name: "hsl", router: Router::Hsl, feed_id: "HSL", center: (60.1699, 24.9384), boundary: (59.9, 60.5, 24.3, 25.5), zoom: 16.0 },
+    Preset { name: "finland", router: Router::Finland, feed_id: "", center: (62.0, 25.0), boundary: (59.5, 70.1, 19.0, 31.6), zoom: 6.0 },
+];
+
+impl Region {
+    pub fn preset(name: &str) -> Option<Region> {
+        let p = PRESETS.iter().find(|p| p.name.eq_ignore_ascii_case(name))?;
+        Some(Region {
+            name: p.name.to_string(),
+            router: p.router,
+            feed_id: p.feed_id.to_string(),
+            boundary: BoundingBox { min_lat: p.boundary.0, max_lat: p.boundary.1, min_lon: p.boundary.2, max_lon: p.boundary.3 },
+            center_lat: p.center.0,
+            center_lon: p.center.1,
+            zoom: p.zoom,
+        })
+    }
+
+    pub fn preset_names() -> Vec<&'static str> {
+        PRESETS.iter().map(|p| p.name).collect()
+    }
+
+    pub fn api_url(&self) -> &'static str {
+        self.router.api_url()
+    }
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region::preset("tampere").expect("oletusalue puuttuu")
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct RegionEntry {
+    preset: Option<String>,
+    router: Option<Router>,
+    feed_id: Option<String>,
+    boundary: Option<BoundingBox>,
+    center_lat: Option<f64>,
+    center_lon: Option<f64>,
+    zoom: Option<f64>,
+}
+
+impl TryFrom<RegionEntry> for Region {
+    type Error = String;
+
+    fn try_from(entry: RegionEntry) -> Result<Self, Self::Error> {
+        let base = match &entry.preset {
+            Some(name) => Region::preset(name).ok_or_else(|| {
+                format!("tuntematon alue \"{}\" (vaihtoehdot: {})", name, Region::preset_names().join(", "))
+            })?,
+            None => Region::default(),
+        };
+        Ok(Region {
+            name: base.name,
+            router: entry.router.unwrap_or(base.router),
+            feed_id: entry.feed_id.unwrap_or(base.feed_id),
+            boundary: entry.boundary.unwrap_or(base.boundary),
+            center_lat: entry.center_lat.unwrap_or(base.center_lat),
+            center_lon: entry.center_lon.unwrap_or(base.center_lon),
+            zoom: entry.zoom.unwrap_or(base.zoom),
+        })
+    }
+}
+
+// [vehicles]-taulu: ajoneuvojen reaaliaikainen sijaintisyöte kartalle
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct VehicleSettings {
+    pub enabled: bool,
+    // mqtt://, mqtts://, http(s):// (GTFS-RT) tai file:// (JSON lines -toisto)
+    pub url: String,
+    pub format: VehicleFeedFormat,
+    // GTFS-RT-aiheen syötetunnus, esim. "tampere". Tyhjä = alueen feed_id;
+    // jos sekin on tyhjä (finland), tilataan kaikki syötteet.
+    pub feed_id: String,
+    // Oma MQTT-aihe; oletuksena muodostetaan seurattavista linjoista
+    pub topic: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VehicleFeedFormat {
+    // Digitransitin /gtfsrt/vp/ (protobuf)
+    Gtfsrt,
+    // HSL:n high-frequency positioning /hfp/v2/ (JSON)
+    Hfp,
+}
+
+impl Default for VehicleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "mqtt://mqtt.digitransit.fi:1883".to_string(),
+            format: VehicleFeedFormat::Gtfsrt,
+            feed_id: String::new(),
+            topic: None,
+        }
+    }
+}
+
+// [logging]-taulu: lokitaso, lokitiedosto ja API-dumpit
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LogSettings {
+    // trace, debug, info, warn tai error. RUST_LOG-ympäristömuuttuja ohittaa tämän.
+    pub level: String,
+    // Kirjoitetaanko päivittäin vaihtuva lokitiedosto
+    pub file: bool,
+    // Lokikansio; oletuksena käyttäjän datakansio (esim. ~/.local/share/bussivahti/logs)
+    pub dir: Option<String>,
+    // Täydet API-pyynnöt ja -vastaukset lokitiedostoon. Vain vianetsintään, tiedostot kasvavat nopeasti.
+    pub dump_api: bool,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            file: true,
+            dir: None,
+            dump_api: false,
+        }
+    }
+}
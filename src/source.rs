@@ -0,0 +1,113 @@
+use crate::models::{now, service_day_start, FetchError, Stop, StopId, StopRequest};
+use crate::network::DigitransitSource;
+use crate::settings::Settings;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// --- LÄHDERAJAPINTA ---
+
+// Kaikki lähtötiedot kulkevat tämän kautta: CLI, GUI ja main.rs eivät tiedä,
+// tuleeko data Digitransitista, tiedostosta vai testin muistista.
+#[async_trait]
+pub trait DepartureSource: Send + Sync {
+    async fn fetch_stops(&self, requests: &[StopRequest]) -> HashMap<StopId, Result<Stop, FetchError>>;
+}
+
+// Valitsee lähteen asetusten perusteella
+pub fn from_settings(settings: &Settings) -> Result<Arc<dyn DepartureSource>> {
+    match &settings.fixture {
+        Some(path) => Ok(Arc::new(FixtureSource::load(path)?)),
+        None => Ok(Arc::new(DigitransitSource::new(&settings.api_key, settings.region.api_url(), &settings.retry))),
+    }
+}
+
+// --- TIEDOSTOLÄHDE (Demo / Offline) ---
+
+// Lukee JSON-tiedoston muotoa { "tampere:0802": { name, lat, lon, stoptimesWithoutPatterns } }.
+// Lähtöajat tulkitaan sekunteina hakuhetkestä, jotta demo näyttää aina tulevia lähtöjä.
+// Tiedoston serviceDay (pakollinen kuten rajapinnassa, esim. 0) jätetään huomiotta
+// ja korvataan kuluvalla palvelupäivällä.
+pub struct FixtureSource {
+    stops: HashMap<StopId, Stop>,
+}
+
+impl FixtureSource {
+    pub fn load(path: &str) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)?;
+        Self::from_json(&raw)
+    }
+
+    pub fn from_json(raw: &str) -> Result<Self> {
+        let stops: HashMap<StopId, Stop> = serde_json::from_str(raw)?;
+        Ok(Self { stops })
+    }
+}
+
+#[async_trait]
+impl DepartureSource for FixtureSource {
+    async fn fetch_stops(&self, requests: &[StopRequest]) -> HashMap<StopId, Result<Stop, FetchError>> {
+        let now = now();
+        let service_day = service_day_start(now.date_naive());
+        let offset = now.timestamp() - service_day;
+
+        requests
+            .iter()
+            .map(|req| {
+                let id = &req.stop_id;
+                let result = match self.stops.get(id) {
+                    Some(stop) => {
+                        let mut stop = stop.clone();
+                        for st in stop.stoptimes_mut() {
+                            st.service_day = service_day;
+                            st.realtime_departure += offset;
+                            st.scheduled_departure += offset;
+                        }
+                        Ok(stop)
+                    }
+                    None => Err(FetchError::not_found(id)),
+                };
+                (id.clone(), result)
+            })
+            .collect()
+    }
+}
+
+// --- MUISTILÄHDE (Testit) ---
+
+// Palauttaa täsmälleen sen datan, joka sille on annettu. Ajat ovat sekunteja
+// serviceDay-kentän hetkestä kuten rajapinnassakin.
+#[derive(Default)]
+pub struct MemorySource {
+    stops: Mutex<HashMap<StopId, Stop>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, stop_id: StopId, stop: Stop) {
+        self.stops.lock().unwrap().insert(stop_id, stop);
+    }
+
+    pub fn remove(&self, stop_id: &StopId) {
+        self.stops.lock().unwrap().remove(stop_id);
+    }
+}
+
+#[async_trait]
+impl DepartureSource for MemorySource {
+    async fn fetch_stops(&self, requests: &[StopRequest]) -> HashMap<StopId, Result<Stop, FetchError>> {
+        let stops = self.stops.lock().unwrap();
+        requests
+            .iter()
+            .map(|req| {
+                let id = &req.stop_id;
+                let result = stops.get(id).cloned().ok_or_else(|| FetchError::not_found(id));
+                (id.clone(), result)
+            })
+            .collect()
+    }
+}
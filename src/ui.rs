@@ -1,86 +1,704 @@
-use crate::models::StopData;
+use crate::keys::{Action, KeyBindings};
+use crate::logging::LogLine;
+use crate::models::{self, AlertSeverity, DepartureInfo, DepartureStatus, ServiceAlert, StopData, StopError, StopId, StopState};
+use crate::settings::{Settings, StopConfig};
+use crossterm::event::{Event, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style, Stylize},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap},
     Frame,
 };
+use chrono::DateTime;
+use chrono_tz::Tz;
 use std::collections::HashMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
-pub fn render(f: &mut Frame, data: &HashMap<String, StopData>, order: &[String]) {
-    // Luodaan layout dynaamisesti pysäkkien määrän mukaan
-    let chunks = Layout::default()
+// Lokipaneelin korkeus riveinä (reunat mukaan lukien)
+const LOG_PANE_HEIGHT: u16 = 12;
+// Pysäkkipaneelin korkeus, pienennettynä vain otsikko ja yksi rivi
+const STOP_PANE_HEIGHT: u16 = 8;
+const COLLAPSED_PANE_HEIGHT: u16 = 3;
+// Ahtaalla paneelit kutistuvat tähän (otsikkorivi ja kaksi lähtöä) ennen sivutusta
+const MIN_PANE_HEIGHT: u16 = 5;
+// Sarakkeen vähimmäisleveys: taulukko ja palkki mahtuvat vielä
+const MIN_COLUMN_WIDTH: u16 = 72;
+
+// --- NÄKYMÄN TILA ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum View {
+    // Kaikki pysäkit paneeleina
+    #[default]
+    Overview,
+    // Valitun pysäkin kaikki lähdöt ja tiedotteet
+    Detail,
+}
+
+// Päivityssilmukalle välitettävät komennot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    Refresh,
+    Pause(bool),
+}
+
+// Lähtötaulun tila piirtojen välillä: valinnat, näkymä ja avoimet ikkunat
+pub struct TuiState {
+    keys: KeyBindings,
+    pub view: View,
+    // Valittu pysäkki näkyvien pysäkkien joukossa
+    pub selected: usize,
+    // Valittu lähtö tietonäkymässä
+    pub departure: usize,
+    pub help: bool,
+    pub show_log: bool,
+    pub paused: bool,
+    // Sivujen automaattinen vaihto; ajastin alkaa alusta jokaisesta käyttäjän toiminnosta
+    rotation: Option<Duration>,
+    last_turn: Instant,
+    // Edellisen piirron sivut (pysäkki-indeksit) ja näkyvät paneelit hiiren osumia varten
+    pages: Vec<Range<usize>>,
+    panels: Vec<(usize, Rect)>,
+}
+
+impl TuiState {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            keys: settings.keys.clone(),
+            view: View::Overview,
+            selected: 0,
+            departure: 0,
+            help: false,
+            show_log: false,
+            paused: false,
+            rotation: settings.page_rotation_secs.map(Duration::from_secs),
+            last_turn: Instant::now(),
+            pages: Vec::new(),
+            panels: Vec::new(),
+        }
+    }
+
+    pub fn page(&self) -> usize {
+        self.pages.iter().position(|p| p.contains(&self.selected)).unwrap_or(0)
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn handle_event(&mut self, event: &Event) -> Option<Command> {
+        self.last_turn = Instant::now();
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => self.handle_key(key),
+            Event::Mouse(mouse) => {
+                self.handle_mouse(mouse);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) -> Option<Command> {
+        let action = self.keys.action(key);
+        // Ohje suljetaan millä tahansa näppäimellä
+        if self.help {
+            self.help = false;
+            return (action == Some(Action::Quit)).then_some(Command::Quit);
+        }
+        match action? {
+            Action::Up => self.step(false),
+            Action::Down => self.step(true),
+            Action::NextStop => self.select_stop(self.selected + 1),
+            Action::PrevStop => self.select_stop(self.selected.saturating_sub(1)),
+            Action::NextPage => self.turn_page(true),
+            Action::PrevPage => self.turn_page(false),
+            Action::Open => self.view = View::Detail,
+            Action::Back => self.view = View::Overview,
+            Action::Refresh => return Some(Command::Refresh),
+            Action::Pause => {
+                self.paused = !self.paused;
+                return Some(Command::Pause(self.paused));
+            }
+            Action::Help => self.help = true,
+            Action::Log => self.show_log = !self.show_log,
+            Action::Quit => return Some(Command::Quit),
+        }
+        None
+    }
+
+    // Klikkaus valitsee paneelin, toinen klikkaus samaan avaa sen. Rulla liikuttaa valintaa.
+    fn handle_mouse(&mut self, mouse: &MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if self.help {
+                    self.help = false;
+                    return;
+                }
+                let hit = self.panels.iter().find(|(_, r)| {
+                    (r.x..r.right()).contains(&mouse.column) && (r.y..r.bottom()).contains(&mouse.row)
+                }).map(|(i, _)| *i);
+                match hit {
+                    Some(i) if i == self.selected => self.view = View::Detail,
+                    Some(i) => self.select_stop(i),
+                    None => {}
+                }
+            }
+            MouseEventKind::ScrollUp => self.step(false),
+            MouseEventKind::ScrollDown => self.step(true),
+            _ => {}
+        }
+    }
+
+    // Yleisnäkymässä ylös/alas vaihtaa pysäkkiä, tietonäkymässä lähtöä
+    fn step(&mut self, down: bool) {
+        match (self.view, down) {
+            (View::Overview, true) => self.select_stop(self.selected + 1),
+            (View::Overview, false) => self.select_stop(self.selected.saturating_sub(1)),
+            (View::Detail, true) => self.departure += 1,
+            (View::Detail, false) => self.departure = self.departure.saturating_sub(1),
+        }
+    }
+
+    // Sivun vaihto valitsee sivun ensimmäisen pysäkin; viimeiseltä sivulta jatketaan alkuun
+    fn turn_page(&mut self, forward: bool) {
+        if self.pages.is_empty() {
+            return;
+        }
+        let count = self.pages.len();
+        let page = if forward { (self.page() + 1) % count } else { (self.page() + count - 1) % count };
+        self.select_stop(self.pages[page].start);
+    }
+
+    // Automaattinen vaihto vain yleisnäkymässä, kun ohje ei ole auki
+    fn rotate(&mut self) {
+        let Some(every) = self.rotation else { return };
+        if self.view == View::Overview && !self.help && self.pages.len() > 1 && self.last_turn.elapsed() >= every {
+            self.turn_page(true);
+            self.last_turn = Instant::now();
+        }
+    }
+
+    fn select_stop(&mut self, index: usize) {
+        if index != self.selected {
+            self.selected = index;
+            self.departure = 0;
+        }
+    }
+
+    // Ensimmäinen näppäin alapalkkiin, esim. "?"
+    fn hint(&self, action: Action) -> String {
+        self.keys.keys(action).first().map(ToString::to_string).unwrap_or_default()
+    }
+}
+
+// Pysäkit näytetään asetusten järjestyksessä, piilotetut ohitetaan
+pub fn render(f: &mut Frame, data: &HashMap<StopId, StopState>, stops: &[StopConfig], log: Option<&[LogLine]>, tui: &mut TuiState) {
+    // Alapalkki: tila, sivu ja tärkeimmät näppäimet
+    let parts = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(
-            order.iter().map(|_| Constraint::Length(8)).collect::<Vec<_>>()
-        )
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
         .split(f.size());
+    let (mut area, footer) = (parts[0], parts[1]);
+
+    // Lokipaneeli vie ruudun alaosan
+    if let Some(lines) = log {
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(LOG_PANE_HEIGHT.min(area.height / 2))])
+            .split(area);
+        area = parts[0];
+        render_log(f, parts[1], lines, &tui.keys.describe(Action::Log));
+    }
+
+    let visible: Vec<&StopConfig> = stops.iter().filter(|s| !s.hidden).collect();
+    // Yksi kellonaika koko ruudulle: laskurit etenevät hakujen välilläkin
+    let now = models::now();
+    tui.selected = tui.selected.min(visible.len().saturating_sub(1));
 
-    for (i, stop_id) in order.iter().enumerate() {
-        if i >= chunks.len() { break; } 
-        
-        if let Some(stop) = data.get(stop_id) {
-            render_stop_table(f, chunks[i], stop);
+    // Sivujako lasketaan joka piirrolla, jotta ikkunan koon muutos näkyy heti
+    let collapsed: Vec<bool> = visible.iter().map(|s| s.collapsed).collect();
+    let pages = grid_pages(area, &collapsed);
+    tui.pages = pages.iter().map(|p| p[0].0..p[p.len() - 1].0 + 1).collect();
+    tui.rotate();
+
+    tui.panels.clear();
+    match visible.get(tui.selected) {
+        Some(config) if tui.view == View::Detail => render_detail(f, area, data.get(&config.id), config, tui, now),
+        _ => {
+            if let Some(page) = pages.get(tui.page()) {
+                tui.panels = page.clone();
+                render_overview(f, page, data, &visible, tui.selected, now);
+            }
+        }
+    }
+
+    render_footer(f, footer, tui);
+    if tui.help {
+        render_help(f, tui);
+    }
+}
+
+// Ruudukko: sarakkeita leveyden mukaan, pysäkit riveittäin asetusten järjestyksessä.
+// Jos kaikki eivät mahdu täysikokoisina, paneelit kutistuvat ensin ja vasta sitten jaetaan sivuille.
+// Palauttaa sivut listoina (pysäkin indeksi, paneelin alue).
+pub fn grid_pages(area: Rect, collapsed: &[bool]) -> Vec<Vec<(usize, Rect)>> {
+    if collapsed.is_empty() {
+        return Vec::new();
+    }
+    let columns = ((area.width / MIN_COLUMN_WIDTH).max(1) as usize).min(collapsed.len());
+    let rows: Vec<Range<usize>> = (0..collapsed.len())
+        .step_by(columns)
+        .map(|start| start..(start + columns).min(collapsed.len()))
+        .collect();
+    // Rivi on matala vain, jos kaikki sen pysäkit on pienennetty
+    let short = |row: &Range<usize>| collapsed[row.clone()].iter().all(|c| *c);
+    let row_height = |row: &Range<usize>, full: u16| if short(row) { COLLAPSED_PANE_HEIGHT } else { full };
+
+    let single_page = (MIN_PANE_HEIGHT..=STOP_PANE_HEIGHT)
+        .rev()
+        .find(|h| rows.iter().map(|r| row_height(r, *h)).sum::<u16>() <= area.height);
+    let full = single_page.unwrap_or(MIN_PANE_HEIGHT);
+
+    // Rivit sivuille; jokaiselle sivulle vähintään yksi rivi, vaikka se ei mahtuisi kokonaan
+    let mut pages: Vec<Vec<&Range<usize>>> = vec![Vec::new()];
+    let mut used = 0;
+    for row in &rows {
+        let height = row_height(row, full);
+        let page = pages.last_mut().expect("vähintään yksi sivu");
+        if !page.is_empty() && used + height > area.height {
+            pages.push(vec![row]);
+            used = height;
+        } else {
+            page.push(row);
+            used += height;
+        }
+    }
+
+    pages
+        .into_iter()
+        .map(|page| {
+            // Sivun ylijäävä tila jaetaan täysikokoisille riveille, enintään normaalikorkeuteen asti
+            let used: u16 = page.iter().map(|r| row_height(r, full)).sum();
+            let tall = page.iter().filter(|r| !short(r)).count() as u16;
+            let grow = area.height.saturating_sub(used).checked_div(tall).unwrap_or(0);
+            let full = (full + grow).min(STOP_PANE_HEIGHT);
+
+            let mut y = area.y;
+            let mut panels = Vec::new();
+            for row in page {
+                let height = row_height(row, full).min(area.bottom().saturating_sub(y));
+                let cells = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+                    .split(Rect::new(area.x, y, area.width, height));
+                panels.extend(row.clone().zip(cells.iter().copied()));
+                y += height;
+            }
+            panels
+        })
+        .collect()
+}
+
+fn render_overview(f: &mut Frame, page: &[(usize, Rect)], data: &HashMap<StopId, StopState>, visible: &[&StopConfig], selected: usize, now: DateTime<Tz>) {
+    for &(i, area) in page {
+        let config = visible[i];
+        let selected = i == selected;
+        let state = data.get(&config.id);
+        if let Some(stop) = state.and_then(|s| s.data.as_ref()) {
+            let error = state.and_then(|s| s.error.as_ref());
+            if config.collapsed {
+                render_stop_summary(f, area, stop, config, error, selected, now);
+            } else {
+                render_stop_table(f, area, stop, config, error, selected, now);
+            }
         } else {
-            let p = Paragraph::new(format!("Haetaan dataa pysäkille {}...", stop_id))
-                .block(Block::default().borders(Borders::ALL).title(stop_id.as_str()));
-            f.render_widget(p, chunks[i]);
+            render_no_data(f, area, config, state, selected);
         }
     }
 }
 
-fn render_stop_table(f: &mut Frame, area: Rect, stop: &StopData) {
-    let header_cells = ["Linja", "Suunta", "Min", "Klo", "Lähtöpylväs"]
+// Ensimmäistä hakua odotetaan tai se epäonnistui
+fn render_no_data(f: &mut Frame, area: Rect, config: &StopConfig, state: Option<&StopState>, selected: bool) {
+    let label = config.name.clone().unwrap_or_else(|| config.id.to_string());
+    let p = if let Some(err) = state.and_then(|s| s.error.as_ref()) {
+        Paragraph::new(format!("Ei dataa: {}", err.message))
+            .style(Style::default().fg(Color::Red))
+            .block(Block::default().borders(Borders::ALL).border_style(panel_style(selected).fg(Color::Red))
+                .title(Line::from(vec![Span::raw(format!(" {} ", label)), error_badge(err)])))
+    } else {
+        Paragraph::new(format!("Haetaan dataa pysäkille {}...", config.id))
+            .block(Block::default().borders(Borders::ALL).border_style(panel_style(selected)).title(label))
+    };
+    f.render_widget(p, area);
+}
+
+// Valittu paneeli korostetaan reunuksella
+fn panel_style(selected: bool) -> Style {
+    if selected {
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    }
+}
+
+// Otsikko: oma nimi tai pysäkin nimi, koodi, kävelyaika ja päivityshetki
+fn stop_title(stop: &StopData, config: &StopConfig, error: Option<&StopError>, selected: bool) -> (Line<'static>, Style) {
+    let name = config.name.as_deref().unwrap_or(&stop.stop_name);
+    let mut title = vec![Span::raw(format!(" {} ({}) ", name, stop.stop_id.code()))];
+    if config.walk_minutes > 0 {
+        title.push(Span::styled(format!("🚶 {} min ", config.walk_minutes), Style::default().fg(Color::Cyan)));
+    }
+    title.push(Span::raw(format!("- Päivitetty {} ", stop.last_updated.format("%H:%M:%S"))));
+    // Vanhentunut data: näytetään edelleen, mutta virhe kerrotaan otsikossa
+    let mut border = panel_style(selected);
+    if let Some(err) = error {
+        title.push(error_badge(err));
+        border = border.fg(Color::Yellow);
+    }
+    (Line::from(title), border)
+}
+
+// Pienennetty paneeli: seuraavat lähdöt yhdellä rivillä
+fn render_stop_summary(f: &mut Frame, area: Rect, stop: &StopData, config: &StopConfig, error: Option<&StopError>, selected: bool, now: DateTime<Tz>) {
+    let (title, border) = stop_title(stop, config, error, selected);
+    let mut spans = Vec::new();
+    for item in stop.upcoming(now).filter(|d| !d.is_cancelled()) {
+        if !spans.is_empty() {
+            spans.push(Span::styled(" · ", Style::default().fg(Color::DarkGray)));
+        }
+        spans.push(Span::styled(item.line.clone(), Style::default().add_modifier(Modifier::BOLD)));
+        spans.push(Span::raw(format!(" {} min", item.minutes_left(now))));
+    }
+    if spans.is_empty() {
+        spans.push(Span::styled("Ei lähtöjä", Style::default().fg(Color::DarkGray)));
+    }
+    let p = Paragraph::new(Line::from(spans))
+        .block(Block::default().borders(Borders::ALL).border_style(border).title(title));
+    f.render_widget(p, area);
+}
+
+fn render_stop_table(f: &mut Frame, area: Rect, stop: &StopData, config: &StopConfig, error: Option<&StopError>, selected: bool, now: DateTime<Tz>) {
+    let header_cells = ["Linja", "Suunta", "Min", "Klo", "Lähtöpylväs", ""]
         .iter()
         .map(|h| Cell::from(*h).style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
     let header = Row::new(header_cells).height(1).bottom_margin(0);
 
-    let rows = stop.departures.iter().map(|item| {
+    let rows = stop.upcoming(now).map(|item| {
         // Värikoodaus minuuttien mukaan
-        let color = if item.minutes_left <= 2 { Color::Red } 
-                   else if item.minutes_left <= 5 { Color::Yellow } 
+        let minutes_left = item.minutes_left(now);
+        let color = if minutes_left <= 2 { Color::Red } 
+                   else if minutes_left <= 5 { Color::Yellow } 
                    else { Color::Green };
 
-        // ASCII-palkki
-        let bar = create_ascii_bar(item.seconds_left, 900, 15, color);
-        let rt_mark = if item.is_realtime { "" } else { "~" };
+        // ASCII-palkki: millisekuntitarkkuus, jotta palkki liikkuu tasaisesti
+        let remaining = item.predicted_time.signed_duration_since(now).num_milliseconds() as f64 / 1000.0;
+        let bar = create_ascii_bar(remaining, 900.0, 15, color);
+
+        // Peruttu lähtö näytetään yliviivattuna, ei piiloteta
+        if item.is_cancelled() {
+            let crossed = Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT);
+            return Row::new(vec![
+                Cell::from(Span::styled(item.line.clone(), crossed.add_modifier(Modifier::BOLD))),
+                Cell::from(Span::styled(item.headsign.clone(), crossed)),
+                Cell::from(Span::styled("PERUTTU", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+                Cell::from(Span::styled(item.scheduled_time.format("%H:%M").to_string(), crossed)),
+                Cell::from(Span::styled(platform_str(item), crossed)),
+                Cell::from(""),
+            ]).height(1);
+        }
+
+        let headsign = match item.status {
+            DepartureStatus::NoPickup => Line::from(vec![
+                Span::styled(item.headsign.clone(), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!(" ({})", item.status.label()), Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)),
+            ]),
+            DepartureStatus::Added => Line::from(vec![
+                Span::raw(item.headsign.clone()),
+                Span::styled(format!(" ({})", item.status.label()), Style::default().fg(Color::LightBlue)),
+            ]),
+            _ => Line::from(item.headsign.clone()),
+        };
 
         let cells = vec![
             Cell::from(Span::styled(item.line.clone(), Style::default().add_modifier(Modifier::BOLD))),
-            Cell::from(item.headsign.clone()),
-            Cell::from(Span::styled(format!("{} min", item.minutes_left), Style::default().fg(color))),
-            Cell::from(format!("{}{}", rt_mark, item.time_str)),
+            Cell::from(headsign),
+            Cell::from(Span::styled(format!("{} min", minutes_left), Style::default().fg(color))),
+            Cell::from(time_with_delay(item)),
+            Cell::from(Span::styled(platform_str(item), Style::default().add_modifier(Modifier::BOLD))),
             Cell::from(bar),
         ];
         Row::new(cells).height(1)
     });
 
-    let title = format!(" {} ({}) - Päivitetty {} ", 
-        stop.stop_name, 
-        stop.stop_id.split(':').nth(1).unwrap_or(""), 
-        stop.last_updated.format("%H:%M:%S")
-    );
+    let (title, border) = stop_title(stop, config, error, selected);
+    let block = Block::default().borders(Borders::ALL).border_style(border).title(title);
+    let mut inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Häiriötiedotteet: yksi banneririvi taulukon yläpuolella
+    let alerts = stop.active_alerts(now);
+    if let Some(first) = alerts.first() {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+        f.render_widget(Paragraph::new(alert_banner(first, alerts.len() - 1)), rows[0]);
+        inner = rows[1];
+    }
     
     let table = Table::new(
         rows,
         [
             Constraint::Length(6), Constraint::Fill(1), Constraint::Length(8), 
-            Constraint::Length(8), Constraint::Length(17),
+            Constraint::Length(12), Constraint::Length(11), Constraint::Length(17),
+        ]
+    )
+    .header(header);
+
+    f.render_widget(table, inner);
+}
+
+// --- TIETONÄKYMÄ ---
+
+// Valitun pysäkin kaikki lähdöt, tiedotteet ja valitun lähdön tarkemmat tiedot
+fn render_detail(f: &mut Frame, area: Rect, state: Option<&StopState>, config: &StopConfig, tui: &mut TuiState, now: DateTime<Tz>) {
+    let Some(stop) = state.and_then(|s| s.data.as_ref()) else {
+        render_no_data(f, area, config, state, true);
+        return;
+    };
+    let error = state.and_then(|s| s.error.as_ref());
+    let (title, border) = stop_title(stop, config, error, true);
+    let block = Block::default().borders(Borders::ALL).border_style(border).title(title);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Tiedotteet kokonaisuudessaan, enintään kolmannes näkymästä
+    let mut notes: Vec<Line> = Vec::new();
+    if let Some(err) = error {
+        notes.push(Line::from(Span::styled(format!("⚠ {}: {}", err.kind, err.message), Style::default().fg(Color::Yellow))));
+    }
+    for alert in stop.active_alerts(now) {
+        notes.push(alert_banner(alert, 0));
+        if !alert.description.is_empty() {
+            notes.push(Line::from(Span::styled(format!("   {}", alert.description), Style::default().fg(Color::Gray))));
+        }
+        if let Some(end) = alert.end {
+            notes.push(Line::from(Span::styled(format!("   Voimassa {} asti", end.format("%d.%m. %H:%M")), Style::default().fg(Color::DarkGray))));
+        }
+    }
+    let notes_height = (notes.len() as u16).min(inner.height / 3);
+    let parts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(notes_height), Constraint::Min(0), Constraint::Length(2)])
+        .split(inner);
+    f.render_widget(Paragraph::new(notes).wrap(Wrap { trim: false }), parts[0]);
+
+    let departures: Vec<&DepartureInfo> = stop.upcoming(now).collect();
+    tui.departure = tui.departure.min(departures.len().saturating_sub(1));
+
+    let header = Row::new(["Linja", "Suunta", "Laituri", "Aikataulu", "Ennuste", "Poikkeama", "Min", "Tila"]
+        .map(|h| Cell::from(h).style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))));
+    let rows = departures.iter().map(|item| {
+        let style = if item.is_boardable() { Style::default() } else { Style::default().fg(Color::DarkGray) };
+        let delay = if item.is_realtime { format!("{:+} min", item.delay_minutes()) } else { "-".to_string() };
+        Row::new(vec![
+            Cell::from(Span::styled(item.line.clone(), Style::default().add_modifier(Modifier::BOLD))),
+            Cell::from(item.headsign.clone()),
+            Cell::from(platform_str(item)),
+            Cell::from(item.scheduled_time.format("%H:%M").to_string()),
+            Cell::from(format!("{}{}", if item.is_realtime { "" } else { "~" }, item.predicted_time.format("%H:%M"))),
+            Cell::from(delay),
+            Cell::from(format!("{} min", item.minutes_left(now))),
+            Cell::from(item.status.label()),
+        ]).style(style)
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(6), Constraint::Fill(1), Constraint::Length(8), Constraint::Length(10),
+            Constraint::Length(8), Constraint::Length(10), Constraint::Length(8), Constraint::Length(11),
         ]
     )
     .header(header)
-    .block(Block::default().borders(Borders::ALL).title(title));
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    .highlight_symbol("▶ ");
+    let mut table_state = TableState::default().with_selected((!departures.is_empty()).then_some(tui.departure));
+    f.render_stateful_widget(table, parts[1], &mut table_state);
 
+    // Valitun lähdön tiedot alimmalla rivillä
+    let summary = match departures.get(tui.departure) {
+        Some(item) => departure_summary(item, now),
+        None => Line::from(Span::styled("Ei lähtöjä lähiaikoina.", Style::default().fg(Color::DarkGray))),
+    };
+    f.render_widget(Paragraph::new(summary).block(Block::default().borders(Borders::TOP)), parts[2]);
+}
+
+// Esim. "3 → Hervanta · laituri B · lähtee 12:07 (aikataulu 12:04, +3 min) · reaaliaika"
+fn departure_summary(item: &DepartureInfo, now: DateTime<Tz>) -> Line<'static> {
+    let mut spans = vec![
+        Span::styled(format!("{} → {}", item.line, item.headsign), Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(format!(" · laituri {}", platform_str(item))),
+    ];
+    let timing = if !item.is_realtime {
+        format!(" · aikataulun mukaan {}, ei reaaliaikaa", item.scheduled_time.format("%H:%M"))
+    } else if item.delay_minutes() != 0 {
+        format!(" · lähtee {} (aikataulu {}, {:+} min)", item.predicted_time.format("%H:%M"), item.scheduled_time.format("%H:%M"), item.delay_minutes())
+    } else {
+        format!(" · lähtee {} ajallaan", item.predicted_time.format("%H:%M"))
+    };
+    spans.push(Span::raw(timing));
+    spans.push(Span::raw(format!(" · {} min {} s", item.seconds_left(now) / 60, item.seconds_left(now) % 60)));
+    spans.push(Span::styled(format!(" · {}", item.status.label()), Style::default().fg(Color::DarkGray)));
+    Line::from(spans)
+}
+
+// --- OHJE JA ALAPALKKI ---
+
+fn render_help(f: &mut Frame, tui: &TuiState) {
+    let mut rows: Vec<Row> = Action::ALL.iter()
+        .map(|a| Row::new(vec![
+            Cell::from(Span::styled(tui.keys.describe(*a), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+            Cell::from(a.label()),
+        ]))
+        .collect();
+    rows.push(Row::new(vec![Cell::from(Span::styled("Hiiri", Style::default().fg(Color::Cyan))), Cell::from("Klikkaus valitsee pysäkin, toinen avaa")]));
+
+    let area = centered(f.size(), 60, rows.len() as u16 + 2);
+    let table = Table::new(rows, [Constraint::Length(18), Constraint::Fill(1)])
+        .block(Block::default().borders(Borders::ALL).title(" Näppäimet ").title_bottom(" [keys] asetuksissa · mikä tahansa näppäin sulkee "));
+    f.render_widget(Clear, area);
     f.render_widget(table, area);
 }
 
-fn create_ascii_bar(val: i64, max: i64, width: usize, color: Color) -> Line<'static> {
-    let ratio = 1.0 - (val as f64 / max as f64).clamp(0.0, 1.0);
-    let filled = (ratio * width as f64).round() as usize;
-    let empty = width - filled;
+fn render_footer(f: &mut Frame, area: Rect, tui: &TuiState) {
+    let key = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    if tui.paused {
+        spans.push(Span::styled(" ⏸ TAUKO ", Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    }
+    let hints = match tui.view {
+        View::Overview => [(Action::Open, "tiedot"), (Action::Refresh, "päivitä"), (Action::Pause, "tauko"), (Action::Help, "ohje"), (Action::Quit, "lopeta")],
+        View::Detail => [(Action::Back, "takaisin"), (Action::NextStop, "seuraava"), (Action::Refresh, "päivitä"), (Action::Help, "ohje"), (Action::Quit, "lopeta")],
+    };
+    for (action, label) in hints {
+        spans.push(Span::styled(format!(" {}", tui.hint(action)), key));
+        spans.push(Span::styled(format!(" {} ", label), Style::default().fg(Color::DarkGray)));
+    }
+
+    // Oikeaan reunaan sijainti: sivu yleisnäkymässä, pysäkki tietonäkymässä
+    let mut position = Vec::new();
+    match tui.view {
+        View::Overview if tui.page_count() > 1 => {
+            if let Some(every) = tui.rotation {
+                position.push(Span::styled(format!("⟳ {} s  ", every.as_secs()), Style::default().fg(Color::DarkGray)));
+            }
+            position.push(Span::styled(format!("{} ", tui.hint(Action::PrevPage)), key));
+            position.push(Span::styled(format!("Sivu {}/{}", tui.page() + 1, tui.page_count()), Style::default().add_modifier(Modifier::BOLD)));
+            position.push(Span::styled(format!(" {} ", tui.hint(Action::NextPage)), key));
+        }
+        View::Detail => {
+            let count = tui.pages.last().map_or(0, |p| p.end);
+            position.push(Span::styled(format!("Pysäkki {}/{} ", tui.selected + 1, count), Style::default().add_modifier(Modifier::BOLD)));
+        }
+        View::Overview => {}
+    }
+    // Sijainti ei jää vihjeiden alle: kapealla ruudulla vihjeet katkeavat
+    let position = Line::from(position);
+    let parts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(position.width() as u16)])
+        .split(area);
+    f.render_widget(Paragraph::new(Line::from(spans)), parts[0]);
+    f.render_widget(Paragraph::new(position).alignment(Alignment::Right), parts[1]);
+}
+
+// Keskitetty alue, enintään annetun kokoinen
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+fn render_log(f: &mut Frame, area: Rect, lines: &[LogLine], toggle: &str) {
+    let visible = area.height.saturating_sub(2) as usize;
+    let rows: Vec<Line> = lines
+        .iter()
+        .skip(lines.len().saturating_sub(visible))
+        .map(|line| {
+            let color = match line.level {
+                tracing::Level::ERROR => Color::Red,
+                tracing::Level::WARN => Color::Yellow,
+                tracing::Level::INFO => Color::Green,
+                _ => Color::DarkGray,
+            };
+            Line::from(vec![
+                Span::styled(line.at.format("%H:%M:%S ").to_string(), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{:<5} ", line.level), Style::default().fg(color)),
+                Span::raw(line.message.clone()),
+            ])
+        })
+        .collect();
+    let p = Paragraph::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(format!(" Loki ({} = piilota) ", toggle)));
+    f.render_widget(p, area);
+}
+
+fn alert_banner(alert: &ServiceAlert, others: usize) -> Line<'static> {
+    let color = match alert.severity {
+        AlertSeverity::Severe => Color::Red,
+        AlertSeverity::Warning | AlertSeverity::Unknown => Color::Yellow,
+        AlertSeverity::Info => Color::Cyan,
+    };
+    let mut spans = vec![Span::styled(" ⚠ ", Style::default().fg(Color::Black).bg(color).add_modifier(Modifier::BOLD))];
+    if !alert.lines.is_empty() {
+        spans.push(Span::styled(format!(" [{}]", alert.lines.join(", ")), Style::default().fg(color).add_modifier(Modifier::BOLD)));
+    }
+    spans.push(Span::styled(format!(" {}", alert.header), Style::default().fg(color)));
+    if others > 0 {
+        spans.push(Span::styled(format!(" (+{} muuta)", others), Style::default().fg(Color::DarkGray)));
+    }
+    Line::from(spans)
+}
+
+fn platform_str(item: &DepartureInfo) -> String {
+    item.platform.clone().unwrap_or_else(|| "-".to_string())
+}
+
+// Aikataulun mukainen aika ja poikkeama, esim. "12:04 (+3)". "~" = ei reaaliaikaa.
+fn time_with_delay(item: &DepartureInfo) -> Line<'static> {
+    if !item.is_realtime {
+        return Line::from(format!("~{}", item.scheduled_time.format("%H:%M")));
+    }
+    let delay = item.delay_minutes();
+    let mut spans = vec![Span::raw(item.scheduled_time.format("%H:%M").to_string())];
+    if delay != 0 {
+        let color = if delay > 0 { Color::LightRed } else { Color::LightCyan };
+        spans.push(Span::styled(format!(" ({:+})", delay), Style::default().fg(color)));
+    }
+    Line::from(spans)
+}
+
+fn error_badge(err: &StopError) -> Span<'static> {
+    Span::styled(
+        format!(" ⚠ {} {} ", err.kind, err.at.format("%H:%M:%S")),
+        Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+    )
+}
+
+// Osittaiset lohkot: merkki jaetaan kahdeksaan osaan, joten palkki kasvaa pienin askelin
+const PARTIAL_BLOCKS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+fn create_ascii_bar(remaining_secs: f64, max_secs: f64, width: usize, color: Color) -> Line<'static> {
+    let ratio = 1.0 - (remaining_secs / max_secs).clamp(0.0, 1.0);
+    let eighths = (ratio * (width * 8) as f64).round() as usize;
+    let filled = eighths / 8;
+    let partial = PARTIAL_BLOCKS[eighths % 8];
+    let empty = width - filled - usize::from(!partial.is_empty());
     Line::from(vec![
-        Span::styled("█".repeat(filled), Style::default().fg(color)),
+        Span::styled(format!("{}{}", "█".repeat(filled), partial), Style::default().fg(color)),
         Span::styled("░".repeat(empty), Style::default().fg(Color::DarkGray)),
     ])
 }
\ No newline at end of file
@@ -0,0 +1,355 @@
+use crate::models::now;
+use crate::registry::StopRegistry;
+use crate::settings::{StopConfig, VehicleFeedFormat, VehicleSettings};
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use prost::Message;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS, Transport};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+
+// Näin vanhat sijainnit poistetaan kartalta (ajoneuvo lopetti ajon tai katosi syötteestä)
+const MAX_AGE_SECS: i64 = 120;
+const HTTP_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// --- TIETORAKENTEET ---
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VehiclePosition {
+    pub id: String,
+    pub line: String,
+    #[serde(default)]
+    pub mode: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    // Kulkusuunta asteina pohjoisesta myötäpäivään
+    #[serde(default)]
+    pub heading: Option<f32>,
+    #[serde(skip, default = "now")]
+    pub updated: DateTime<Tz>,
+}
+
+impl VehiclePosition {
+    pub fn is_tram(&self) -> bool {
+        self.mode.as_deref().is_some_and(|m| m.eq_ignore_ascii_case("tram"))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VehicleLayer {
+    pub positions: HashMap<String, VehiclePosition>,
+    // Viimeisin yhteysvirhe, näytetään käyttöliittymässä
+    pub error: Option<String>,
+}
+
+impl VehicleLayer {
+    fn update(&mut self, vehicle: VehiclePosition) {
+        self.positions.insert(vehicle.id.clone(), vehicle);
+        self.error = None;
+    }
+
+    // Seurannasta poistettujen linjojen ajoneuvot pois heti, ei vasta MAX_AGE_SECS:n jälkeen
+    fn retain_lines(&mut self, lines: &Option<Vec<String>>) {
+        self.positions.retain(|_, v| accept(lines, v));
+    }
+
+    fn prune(&mut self) {
+        let now = now();
+        self.positions.retain(|_, v| now.signed_duration_since(v.updated).num_seconds() < MAX_AGE_SECS);
+    }
+}
+
+pub type VehicleStore = Arc<Mutex<VehicleLayer>>;
+
+// Näkyvien pysäkkien linjat järjestettynä, jotta pysäkkien siirto ei vaihda aiheita.
+// None = kaikki linjat ("ALL" jollakin pysäkillä).
+pub fn tracked_lines(stops: &[StopConfig]) -> Option<Vec<String>> {
+    let mut lines: Vec<String> = Vec::new();
+    for config in stops.iter().filter(|s| !s.hidden) {
+        if config.lines.iter().any(|l| l == "ALL") {
+            return None;
+        }
+        lines.extend(config.lines.iter().cloned());
+    }
+    lines.sort();
+    lines.dedup();
+    Some(lines)
+}
+
+// --- SYÖTTEEN LUKU ---
+
+// Pyörii taustalla koko ohjelman ajan ja yhdistää uudelleen virheen jälkeen.
+// Linjat luetaan rekisteristä; kun ne muuttuvat, yhteys avataan uusin aihein.
+pub async fn run(config: VehicleSettings, registry: StopRegistry, store: VehicleStore) {
+    let mut changes = registry.subscribe();
+    loop {
+        let lines = tracked_lines(&registry.snapshot());
+        store.lock().unwrap().retain_lines(&lines);
+
+        tokio::select! {
+            result = read_feed(&config, &lines, &store) => {
+                if let Err(e) = result {
+                    tracing::warn!(url = %config.url, error = %e, "Ajoneuvosyöte katkesi, yhdistetään uudelleen");
+                    store.lock().unwrap().error = Some(e.to_string());
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+            _ = lines_changed(&mut changes, &registry, &lines) => {
+                tracing::debug!("Seurattavat linjat muuttuivat, tilataan ajoneuvot uudelleen");
+            }
+        }
+    }
+}
+
+async fn read_feed(config: &VehicleSettings, lines: &Option<Vec<String>>, store: &VehicleStore) -> Result<()> {
+    match config.url.split_once("://") {
+        Some(("mqtt", addr)) => run_mqtt(config, addr, false, lines, store).await,
+        Some(("mqtts", addr)) => run_mqtt(config, addr, true, lines, store).await,
+        Some(("http" | "https", _)) => poll_http(config, lines, store).await,
+        Some(("file", path)) => replay_file(path, lines, store).await,
+        Some((scheme, _)) => Err(anyhow!("Tuntematon syötteen osoite: {}://", scheme)),
+        None => replay_file(&config.url, lines, store).await,
+    }
+}
+
+// Palaa vasta, kun rekisterin muutos vaihtaa seurattavia linjoja (ei esim. nimen muokkauksesta)
+async fn lines_changed(changes: &mut watch::Receiver<()>, registry: &StopRegistry, lines: &Option<Vec<String>>) {
+    while changes.changed().await.is_ok() {
+        if tracked_lines(&registry.snapshot()) != *lines {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+fn accept(lines: &Option<Vec<String>>, vehicle: &VehiclePosition) -> bool {
+    lines.as_ref().is_none_or(|l| l.contains(&vehicle.line))
+}
+
+fn store_all(store: &VehicleStore, lines: &Option<Vec<String>>, vehicles: Vec<VehiclePosition>) {
+    let mut layer = store.lock().unwrap();
+    for vehicle in vehicles.into_iter().filter(|v| accept(lines, v)) {
+        layer.update(vehicle);
+    }
+    layer.prune();
+}
+
+// --- MQTT (Digitransit / HSL / paikallinen välittäjä) ---
+
+async fn run_mqtt(config: &VehicleSettings, addr: &str, tls: bool, lines: &Option<Vec<String>>, store: &VehicleStore) -> Result<()> {
+    let (host, port) = match addr.trim_end_matches('/').rsplit_once(':') {
+        Some((host, port)) => (host, port.parse()?),
+        None => (addr.trim_end_matches('/'), if tls { 8883 } else { 1883 }),
+    };
+
+    let client_id = format!("bussivahti-{:08x}", rand::random::<u32>());
+    let mut options = MqttOptions::new(client_id, host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    if tls {
+        options.set_transport(Transport::tls_with_default_config());
+    }
+
+    let (client, mut eventloop) = AsyncClient::new(options, 100);
+    for topic in topics(config, lines) {
+        client.subscribe(topic, QoS::AtMostOnce).await?;
+    }
+
+    loop {
+        if let Event::Incoming(Packet::Publish(publish)) = eventloop.poll().await? {
+            let vehicles = match config.format {
+                VehicleFeedFormat::Gtfsrt => decode_gtfsrt(&publish.payload, Some(&publish.topic)),
+                VehicleFeedFormat::Hfp => decode_hfp(&publish.payload, &publish.topic),
+            };
+            store_all(store, lines, vehicles);
+        }
+    }
+}
+
+// GTFS-RT-aiheessa linjan lyhyt nimi on 16. kenttä syötteen tunnuksen jälkeen, joten
+// välittäjä suodattaa linjat valmiiksi. HFP:n aiheessa on vain reitin sisäinen id.
+pub fn topics(config: &VehicleSettings, lines: &Option<Vec<String>>) -> Vec<String> {
+    if let Some(topic) = &config.topic {
+        return vec![topic.clone()];
+    }
+    // Koko maan alueella ei ole yhtä syötettä: tyhjä tunnus tilaa kaikki
+    let feed = if config.feed_id.is_empty() { "+" } else { config.feed_id.as_str() };
+    match (config.format, lines) {
+        (VehicleFeedFormat::Gtfsrt, Some(lines)) => lines
+            .iter()
+            .map(|line| format!("/gtfsrt/vp/{}/{}{}/#", feed, "+/".repeat(14), line))
+            .collect(),
+        (VehicleFeedFormat::Gtfsrt, None) => vec![format!("/gtfsrt/vp/{}/#", feed)],
+        (VehicleFeedFormat::Hfp, _) => vec!["/hfp/v2/journey/ongoing/vp/#".to_string()],
+    }
+}
+
+// --- GTFS-RT (protobuf) ---
+
+// Vain tarvittavat kentät gtfs-realtime.proto:sta
+#[derive(Clone, PartialEq, Message)]
+struct FeedMessage {
+    #[prost(message, repeated, tag = "2")]
+    entity: Vec<FeedEntity>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FeedEntity {
+    #[prost(string, tag = "1")]
+    id: String,
+    #[prost(message, optional, tag = "4")]
+    vehicle: Option<GtfsVehicle>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GtfsVehicle {
+    #[prost(message, optional, tag = "1")]
+    trip: Option<TripDescriptor>,
+    #[prost(message, optional, tag = "2")]
+    position: Option<GtfsPosition>,
+    #[prost(message, optional, tag = "8")]
+    vehicle: Option<VehicleDescriptor>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TripDescriptor {
+    #[prost(string, optional, tag = "5")]
+    route_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GtfsPosition {
+    #[prost(float, tag = "1")]
+    latitude: f32,
+    #[prost(float, tag = "2")]
+    longitude: f32,
+    #[prost(float, optional, tag = "3")]
+    bearing: Option<f32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct VehicleDescriptor {
+    #[prost(string, optional, tag = "1")]
+    id: Option<String>,
+}
+
+pub fn decode_gtfsrt(payload: &[u8], topic: Option<&str>) -> Vec<VehiclePosition> {
+    let Ok(feed) = FeedMessage::decode(payload) else { return Vec::new() };
+
+    // /gtfsrt/vp/<feed>/<agency_id>/<agency_name>/<mode>/.../<short_name>/<color>/
+    let fields: Vec<&str> = topic.map(|t| t.split('/').collect()).unwrap_or_default();
+    let topic_mode = fields.get(6).filter(|s| !s.is_empty()).map(|s| s.to_string());
+    let topic_line = fields.get(18).filter(|s| !s.is_empty()).map(|s| s.to_string());
+
+    feed.entity
+        .into_iter()
+        .filter_map(|entity| {
+            let vehicle = entity.vehicle?;
+            let position = vehicle.position?;
+            // Ilman aihetta (HTTP-syöte) linja päätellään reitin id:stä: "tampere:3" -> "3"
+            let line = topic_line.clone().or_else(|| {
+                let route = vehicle.trip?.route_id?;
+                Some(route.rsplit(':').next().unwrap_or(&route).to_string())
+            })?;
+            let id = vehicle.vehicle.and_then(|v| v.id).unwrap_or(entity.id);
+            Some(VehiclePosition {
+                id,
+                line,
+                mode: topic_mode.clone(),
+                lat: position.latitude as f64,
+                lon: position.longitude as f64,
+                heading: position.bearing,
+                updated: now(),
+            })
+        })
+        .collect()
+}
+
+// --- HFP (HSL, JSON) ---
+
+#[derive(Deserialize)]
+struct HfpMessage {
+    #[serde(rename = "VP")]
+    vp: HfpPayload,
+}
+
+#[derive(Deserialize)]
+struct HfpPayload {
+    desi: Option<String>,
+    oper: Option<u32>,
+    veh: Option<u32>,
+    lat: Option<f64>,
+    long: Option<f64>,
+    hdg: Option<f32>,
+}
+
+pub fn decode_hfp(payload: &[u8], topic: &str) -> Vec<VehiclePosition> {
+    let Ok(msg) = serde_json::from_slice::<HfpMessage>(payload) else { return Vec::new() };
+    let vp = msg.vp;
+    // /hfp/v2/journey/ongoing/vp/<mode>/...
+    let mode = topic.split('/').nth(6).map(|s| s.to_string());
+
+    match (vp.desi, vp.lat, vp.long) {
+        (Some(line), Some(lat), Some(lon)) => vec![VehiclePosition {
+            id: format!("{}/{}", vp.oper.unwrap_or(0), vp.veh.unwrap_or(0)),
+            line,
+            mode,
+            lat,
+            lon,
+            heading: vp.hdg,
+            updated: now(),
+        }],
+        _ => Vec::new(),
+    }
+}
+
+// --- HTTP (GTFS-RT VehiclePositions) ---
+
+async fn poll_http(config: &VehicleSettings, lines: &Option<Vec<String>>, store: &VehicleStore) -> Result<()> {
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+    loop {
+        let bytes = client.get(&config.url).send().await?.error_for_status()?.bytes().await?;
+        store_all(store, lines, decode_gtfsrt(&bytes, None));
+        tokio::time::sleep(HTTP_POLL_INTERVAL).await;
+    }
+}
+
+// --- TIEDOSTON TOISTO (kehitys) ---
+
+// JSON lines: { "t": 0.0, "id": "42", "line": "3", "lat": 61.49, "lon": 23.76, "heading": 90 }
+// Kenttä t on sekunteja tallenteen alusta. Tallenne toistetaan silmukassa.
+#[derive(Deserialize)]
+struct ReplayRecord {
+    t: f64,
+    #[serde(flatten)]
+    vehicle: VehiclePosition,
+}
+
+async fn replay_file(path: &str, lines: &Option<Vec<String>>, store: &VehicleStore) -> Result<()> {
+    let raw = std::fs::read_to_string(path)?;
+    let records: Vec<ReplayRecord> = raw
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    if records.is_empty() {
+        return Err(anyhow!("Tyhjä toistotiedosto: {}", path));
+    }
+
+    loop {
+        let mut elapsed = 0.0;
+        for record in &records {
+            if record.t > elapsed {
+                tokio::time::sleep(Duration::from_secs_f64(record.t - elapsed)).await;
+                elapsed = record.t;
+            }
+            let mut vehicle = record.vehicle.clone();
+            vehicle.updated = now();
+            store_all(store, lines, vec![vehicle]);
+        }
+    }
+}
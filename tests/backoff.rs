@@ -0,0 +1,93 @@
+use bussivahti_pro::models::{FetchError, FetchErrorKind, StopData, StopId};
+use bussivahti_pro::network::{backoff_delay, retry_after, PollBackoff};
+use bussivahti_pro::settings::{RetryPolicy, Settings};
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn policy() -> RetryPolicy {
+    RetryPolicy { timeout_secs: 10, max_retries: 2, backoff_base_ms: 1000, backoff_max_secs: 8 }
+}
+
+fn settings() -> Settings {
+    Settings::parse(
+        r#"
+version = 1
+[api]
+api_key = "k"
+[app]
+update_interval = 2
+[retry]
+backoff_base_ms = 1000
+backoff_max_secs = 60
+[stops]
+"0802" = ["ALL"]
+"#,
+    )
+    .unwrap()
+}
+
+type Results = HashMap<StopId, Result<StopData, FetchError>>;
+
+fn failed(error: FetchError) -> Results {
+    HashMap::from([("tampere:0802".parse().unwrap(), Err(error))])
+}
+
+#[test]
+fn backoff_delay_stays_within_jitter_bounds_and_cap() {
+    let policy = policy();
+    for _ in 0..50 {
+        // Yritys n: [base * 2^n / 2, base * 2^n]
+        for (attempt, full) in [(0, 1000), (1, 2000), (2, 4000)] {
+            let delay = backoff_delay(&policy, attempt);
+            assert!(delay >= Duration::from_millis(full / 2) && delay <= Duration::from_millis(full), "{:?}", delay);
+        }
+        // Yläraja 8 s, myös hyvin suurilla yrityskerroilla
+        for attempt in [3, 10, 64, u32::MAX] {
+            let delay = backoff_delay(&policy, attempt);
+            assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(8), "{:?}", delay);
+        }
+    }
+}
+
+#[test]
+fn poll_backoff_grows_only_while_api_is_down() {
+    let mut backoff = PollBackoff::new(&settings());
+    let interval = Duration::from_secs(2);
+
+    // Pysyvä virhe (esim. väärä avain) ei hidasta päivitystä
+    assert_eq!(backoff.next_delay(&failed(FetchError::new(FetchErrorKind::Auth, "401"))), interval);
+    assert_eq!(backoff.next_delay(&HashMap::new()), interval);
+
+    let down = || failed(FetchError::new(FetchErrorKind::Timeout, "aikakatkaisu"));
+    let first = backoff.next_delay(&down());
+    assert!(first >= interval && first <= Duration::from_secs(2), "{:?}", first);
+    for _ in 0..3 {
+        backoff.next_delay(&down());
+    }
+    // Neljä epäonnistumista: 1 s * 2^4 = 16 s, jitterillä vähintään 8 s
+    assert!(backoff.next_delay(&down()) >= Duration::from_secs(8));
+
+    // Onnistunut kierros palauttaa normaalin välin ja nollaa laskurin
+    assert_eq!(backoff.next_delay(&failed(FetchError::not_found(&"tampere:0802".parse().unwrap()))), interval);
+    assert!(backoff.next_delay(&down()) <= Duration::from_secs(2));
+}
+
+#[test]
+fn poll_backoff_respects_retry_after() {
+    let mut backoff = PollBackoff::new(&settings());
+    let mut error = FetchError::from_status(429, "HTTP 429");
+    error.retry_after = Some(30);
+    assert!(backoff.next_delay(&failed(error)) >= Duration::from_secs(30));
+}
+
+#[test]
+fn parses_retry_after_seconds() {
+    let header = |value: &'static str| HeaderMap::from_iter([(RETRY_AFTER, HeaderValue::from_static(value))]);
+    assert_eq!(retry_after(&header("120")), Some(120));
+    assert_eq!(retry_after(&header(" 5 ")), Some(5));
+    // HTTP-päivämäärää ei tueta
+    assert_eq!(retry_after(&header("Wed, 21 Oct 2026 07:28:00 GMT")), None);
+    assert_eq!(retry_after(&header("-1")), None);
+    assert_eq!(retry_after(&HeaderMap::new()), None);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use bussivahti_pro::models::{FetchErrorKind, Stop, StopId, StopRequest};
+use bussivahti_pro::network::{build_batch_query, graphql_string, resolve_aliases, MAX_STOPS_PER_QUERY};
+use serde_json::json;
+use std::collections::HashMap;
+
+fn request(n: usize) -> StopRequest {
+    StopRequest {
+        stop_id: format!("tampere:{:04}", n).parse().unwrap(),
+        start_time: 0,
+        time_range: 3600,
+        departures_per_pattern: 5,
+    }
+}
+
+fn stop(name: &str) -> Stop {
+    let mut stop = common::stop_json(Vec::new());
+    stop["name"] = json!(name);
+    serde_json::from_value(stop).unwrap()
+}
+
+#[test]
+fn eleven_stops_split_into_two_queries_with_own_aliases() {
+    let requests: Vec<StopRequest> = (0..=MAX_STOPS_PER_QUERY).map(request).collect();
+    let chunks: Vec<&[StopRequest]> = requests.chunks(MAX_STOPS_PER_QUERY).collect();
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[1].len(), 1);
+
+    // Aliakset alkavat jokaisessa kyselyssä nollasta
+    let first = build_batch_query(chunks[0]);
+    assert!(first.contains(r#"s9: stop(id: "tampere:0009")"#));
+    assert!(!first.contains("s10:"));
+    let second = build_batch_query(chunks[1]);
+    assert!(second.contains(r#"s0: stop(id: "tampere:0010")"#));
+
+    let data = HashMap::from([("s0".to_string(), Some(stop("Viimeinen")))]);
+    let results = resolve_aliases(chunks[1], data);
+    let id: StopId = "tampere:0010".parse().unwrap();
+    assert_eq!(results[&id].as_ref().unwrap().name, "Viimeinen");
+}
+
+#[test]
+fn missing_or_null_alias_is_not_found() {
+    let requests = [request(1), request(2), request(3)];
+    let data = HashMap::from([("s0".to_string(), Some(stop("Eka"))), ("s1".to_string(), None)]);
+    let results = resolve_aliases(&requests, data);
+
+    assert_eq!(results[&requests[0].stop_id].as_ref().unwrap().name, "Eka");
+    for req in &requests[1..] {
+        assert_eq!(results[&req.stop_id].as_ref().unwrap_err().kind, FetchErrorKind::NotFound);
+    }
+}
+
+#[test]
+fn graphql_strings_are_escaped() {
+    assert_eq!(graphql_string("HSL:1040129"), r#""HSL:1040129""#);
+    assert_eq!(graphql_string(r#"a"b\c"#), r#""a\"b\\c""#);
+    assert_eq!(graphql_string("rivi\ntab\t\u{1}"), r#""rivi\ntab\t\u0001""#);
+    assert_eq!(graphql_string("Hervanta ä"), r#""Hervanta ä""#);
+}
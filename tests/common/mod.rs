@@ -0,0 +1,57 @@
+// Yhteiset testiaineistot: pysäkit ja lähdöt rajapinnan JSON-muodossa.
+// Jokainen testitiedosto käyttää vain osaa apufunktioista.
+#![allow(dead_code)]
+
+use bussivahti_pro::models::{service_day_start, Stop, TIMEZONE};
+use bussivahti_pro::settings::StopConfig;
+use chrono::{DateTime, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+
+pub fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+// Sekunteja palvelupäivän alusta; yli 24 h on sallittu kuten GTFS:ssä
+pub fn hms(h: i64, m: i64) -> i64 {
+    h * 3600 + m * 60
+}
+
+// Testien vakiohetki: tavallinen arkipäivä kesäajassa
+pub fn noon() -> DateTime<Tz> {
+    TIMEZONE.with_ymd_and_hms(2026, 6, 15, 12, 0, 0).unwrap()
+}
+
+// Aikataulun mukainen reaaliaikainen lähtö linjalla `line`
+pub fn stoptime_json(service_day: i64, seconds: i64, line: &str) -> Value {
+    json!({
+        "realtimeDeparture": seconds,
+        "scheduledDeparture": seconds,
+        "realtime": true,
+        "serviceDay": service_day,
+        "trip": { "route": { "shortName": line }, "tripHeadsign": "Hervanta" }
+    })
+}
+
+// Lähtö `minute` minuuttia noon()-hetken jälkeen
+pub fn after_noon(minute: i64, line: &str) -> Value {
+    stoptime_json(service_day_start(noon().date_naive()), hms(12, minute), line)
+}
+
+pub fn stop_json(stoptimes: Vec<Value>) -> Value {
+    json!({
+        "name": "Keskustori H",
+        "lat": 61.4978,
+        "lon": 23.7603,
+        "stoptimesWithoutPatterns": stoptimes
+    })
+}
+
+pub fn stop(stoptimes: Vec<Value>) -> Stop {
+    serde_json::from_value(stop_json(stoptimes)).unwrap()
+}
+
+// Pysäkki tampere:0001 annetuilla linjoilla ("ALL" = kaikki)
+pub fn config(lines: &[&str]) -> StopConfig {
+    StopConfig::new("tampere:0001".parse().unwrap(), lines.iter().map(|l| l.to_string()).collect())
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use bussivahti_pro::models::{DepartureStatus, StopTime};
+use bussivahti_pro::network::build_stop_data;
+use chrono::Duration;
+use common::{after_noon, config, noon, stop};
+use serde_json::{json, Value};
+
+// Lähtö klo 12:mm, tila ja nousutapa valinnaisia
+fn departure(minute: i64, line: &str, state: Option<&str>, pickup: Option<&str>) -> Value {
+    let mut st = after_noon(minute, line);
+    st["realtimeState"] = json!(state);
+    st["pickupType"] = json!(pickup);
+    st
+}
+
+fn status(state: Option<&str>, pickup: Option<&str>) -> DepartureStatus {
+    let st: StopTime = serde_json::from_value(departure(0, "3", state, pickup)).unwrap();
+    st.status()
+}
+
+#[test]
+fn maps_realtime_state_and_pickup_type() {
+    assert_eq!(status(Some("CANCELED"), None), DepartureStatus::Cancelled);
+    assert_eq!(status(Some("ADDED"), None), DepartureStatus::Added);
+    assert_eq!(status(Some("SCHEDULED"), Some("NONE")), DepartureStatus::NoPickup);
+    assert_eq!(status(Some("UPDATED"), Some("SCHEDULED")), DepartureStatus::Updated);
+    assert_eq!(status(None, None), DepartureStatus::Scheduled);
+    // Peruttu voittaa nousukiellon
+    assert_eq!(status(Some("CANCELED"), Some("NONE")), DepartureStatus::Cancelled);
+}
+
+#[test]
+fn next_boardable_skips_cancelled_and_no_pickup() {
+    let stop = stop(vec![
+        departure(5, "1", Some("CANCELED"), None),
+        departure(6, "2", Some("UPDATED"), Some("NONE")),
+        departure(8, "3", Some("UPDATED"), None),
+        departure(9, "4", Some("ADDED"), None),
+    ]);
+    let now = noon();
+    let data = build_stop_data(stop, &config(&["ALL"]), now);
+
+    // Perutut ja nousukiellot näytetään listassa, mutta niihin ei pääse kyytiin
+    assert_eq!(data.upcoming(now).count(), 4);
+    assert_eq!(data.next_boardable(now).unwrap().line, "3");
+
+    // Linjan 3 lähdettyä seuraava on lisävuoro
+    let later = now + Duration::seconds(8 * 60 + 30);
+    assert_eq!(data.next_boardable(later).unwrap().line, "4");
+    let last = now + Duration::minutes(10);
+    assert!(data.next_boardable(last).is_none());
+}
@@ -0,0 +1,52 @@
+use bussivahti_pro::keys::{Action, Key, KeyBindings};
+use bussivahti_pro::schema;
+use bussivahti_pro::settings::Settings;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+}
+
+#[test]
+fn parses_and_matches_keys() {
+    let ctrl_r: Key = "Ctrl+r".parse().unwrap();
+    assert!(ctrl_r.matches(&press(KeyCode::Char('r'), KeyModifiers::CONTROL)));
+    assert!(!ctrl_r.matches(&press(KeyCode::Char('r'), KeyModifiers::NONE)));
+    assert_eq!("f5".parse::<Key>().unwrap().to_string(), "F5");
+    assert_eq!("Ctrl++".parse::<Key>().unwrap().to_string(), "Ctrl++");
+    assert!("Hyper+x".parse::<Key>().is_err());
+    assert!("Nappi".parse::<Key>().is_err());
+
+    // "?" tulee monilla päätteillä Shiftin kanssa
+    let keys = KeyBindings::default();
+    assert_eq!(keys.action(&press(KeyCode::Char('?'), KeyModifiers::SHIFT)), Some(Action::Help));
+    assert_eq!(keys.action(&press(KeyCode::BackTab, KeyModifiers::SHIFT)), Some(Action::PrevStop));
+}
+
+#[test]
+fn loads_keys_section() {
+    let text = r#"
+[api]
+api_key = "k"
+[app]
+update_interval = 30
+[[stops]]
+id = "tampere:0802"
+[keys]
+refresh = ["F5", "r"]
+quit = ["x"]
+"#;
+    let settings = Settings::parse(text).unwrap();
+    assert!(settings.warnings.is_empty());
+    assert_eq!(settings.keys.action(&press(KeyCode::F(5), KeyModifiers::NONE)), Some(Action::Refresh));
+    assert_eq!(settings.keys.action(&press(KeyCode::Char('x'), KeyModifiers::NONE)), Some(Action::Quit));
+    assert_eq!(settings.keys.action(&press(KeyCode::Char('q'), KeyModifiers::NONE)), None);
+    // Puuttuvat toiminnot pitävät oletuksensa
+    assert_eq!(settings.keys.action(&press(KeyCode::Char('p'), KeyModifiers::NONE)), Some(Action::Pause));
+
+    // Tuntematon näppäin on virhe, oletussidonnan kanssa päällekkäinen varoitus
+    let bad = text.replace(r#"quit = ["x"]"#, r#"quit = ["Esc", "Meta+q"]"#);
+    let problems = schema::check(&bad);
+    let found: Vec<(Option<usize>, &str, bool)> = problems.iter().map(|p| (p.line, p.key.as_str(), p.is_error())).collect();
+    assert_eq!(found, vec![(Some(10), "keys.quit", true), (Some(10), "keys.quit", false)]);
+}
@@ -0,0 +1,35 @@
+use bussivahti_pro::logging::env_filter;
+use bussivahti_pro::settings::LogSettings;
+
+fn directives(dump_api: bool, rust_log: Option<&str>) -> Vec<String> {
+    let settings = LogSettings { dump_api, ..LogSettings::default() };
+    env_filter(&settings, rust_log).to_string().split(',').map(str::to_string).collect()
+}
+
+#[test]
+fn api_dump_stays_off_unless_requested() {
+    // Asetusten taso: dumpit pois, hyper hiljennetty
+    let default = directives(false, None);
+    assert!(default.contains(&"api_dump=off".to_string()), "{:?}", default);
+    assert!(default.contains(&"hyper=info".to_string()), "{:?}", default);
+
+    // Pelkkä RUST_LOG=trace ei kirjaa pyyntöjä
+    let trace = directives(false, Some("trace"));
+    assert!(trace.contains(&"api_dump=off".to_string()), "{:?}", trace);
+
+    // Kohteen nimeäminen tai dump_api = true avaa dumpit
+    let named = directives(false, Some("info,api_dump=trace"));
+    assert!(named.contains(&"api_dump=trace".to_string()) && !named.contains(&"api_dump=off".to_string()), "{:?}", named);
+    let enabled = directives(true, None);
+    assert!(enabled.contains(&"api_dump=trace".to_string()) && !enabled.contains(&"api_dump=off".to_string()), "{:?}", enabled);
+}
+
+#[test]
+fn user_filter_is_not_overridden() {
+    let filter = directives(false, Some("info,hyper=debug"));
+    assert!(filter.contains(&"hyper=debug".to_string()), "{:?}", filter);
+    assert!(!filter.contains(&"hyper=info".to_string()), "{:?}", filter);
+
+    // Kelvoton RUST_LOG = asetusten taso
+    assert!(directives(false, Some("info,[[")).contains(&"hyper=info".to_string()));
+}
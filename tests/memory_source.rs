@@ -0,0 +1,55 @@
+mod common;
+
+use bussivahti_pro::models::{self, service_day_start, FetchErrorKind, Stop, StopId};
+use bussivahti_pro::network::fetch_all_stops;
+use bussivahti_pro::settings::StopConfig;
+use bussivahti_pro::source::MemorySource;
+
+fn id(s: &str) -> StopId {
+    s.parse().unwrap()
+}
+
+// Pysäkki, jolta lähtee linja 3 annetun määrän minuutteja nykyhetkestä
+fn stop_departing_in(minutes: i64) -> Stop {
+    let now = models::now();
+    let service_day = service_day_start(now.date_naive());
+    let seconds = now.timestamp() - service_day + minutes * 60;
+    common::stop(vec![common::stoptime_json(service_day, seconds, "3")])
+}
+
+#[tokio::test]
+async fn fetches_found_and_missing_stops() {
+    let source = MemorySource::new();
+    source.insert(id("tampere:0001"), stop_departing_in(5));
+    let stops = vec![
+        StopConfig::new(id("tampere:0001"), vec!["ALL".to_string()]),
+        StopConfig::new(id("tampere:9999"), vec!["ALL".to_string()]),
+    ];
+
+    let results = fetch_all_stops(&source, &stops).await;
+    assert_eq!(results.len(), 2);
+    let data = results[&id("tampere:0001")].as_ref().unwrap();
+    assert_eq!(data.stop_name, "Keskustori H");
+    assert_eq!(data.departures.len(), 1);
+    assert_eq!(data.departures[0].line, "3");
+    let err = results[&id("tampere:9999")].as_ref().unwrap_err();
+    assert_eq!(err.kind, FetchErrorKind::NotFound);
+
+    // Poistettu pysäkki ei enää löydy
+    source.remove(&id("tampere:0001"));
+    let results = fetch_all_stops(&source, &stops[..1]).await;
+    assert_eq!(results[&id("tampere:0001")].as_ref().unwrap_err().kind, FetchErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn skips_hidden_stops() {
+    let source = MemorySource::new();
+    source.insert(id("tampere:0001"), stop_departing_in(5));
+    source.insert(id("tampere:0002"), stop_departing_in(5));
+    let mut hidden = StopConfig::new(id("tampere:0002"), vec!["ALL".to_string()]);
+    hidden.hidden = true;
+    let stops = vec![StopConfig::new(id("tampere:0001"), vec!["ALL".to_string()]), hidden];
+
+    let results = fetch_all_stops(&source, &stops).await;
+    assert_eq!(results.keys().collect::<Vec<_>>(), [&id("tampere:0001")]);
+}
@@ -0,0 +1,120 @@
+use bussivahti_pro::schema;
+use bussivahti_pro::settings::{Overrides, Region, Router, Settings};
+use bussivahti_pro::vehicles::topics;
+
+fn with_region(region: &str) -> Result<Settings, String> {
+    let text = format!(
+        r#"
+version = 1
+[api]
+api_key = "k"
+[app]
+update_interval = 15
+[region]
+{}
+[stops]
+"#,
+        region
+    );
+    Settings::parse(&text).map_err(|problems| problems.iter().map(|p| p.message.clone()).collect::<Vec<_>>().join("; "))
+}
+
+#[test]
+fn looks_up_presets_by_name() {
+    let turku = Region::preset("Turku").unwrap();
+    assert_eq!(turku.name, "turku");
+    assert_eq!(turku.feed_id, "FOLI");
+    assert_eq!(turku.router, Router::Waltti);
+    assert_eq!(Region::preset("hsl").unwrap().api_url(), "https://api.digitransit.fi/routing/v2/hsl/gtfs/v1");
+    assert!(Region::preset("tukholma").is_none());
+
+    // Jokainen listattu nimi löytyy ja keskipiste on hakualueen sisällä
+    for name in Region::preset_names() {
+        let r = Region::preset(name).unwrap();
+        assert!(r.boundary.min_lat < r.center_lat && r.center_lat < r.boundary.max_lat, "{}", name);
+        assert!(r.boundary.min_lon < r.center_lon && r.center_lon < r.boundary.max_lon, "{}", name);
+    }
+    assert_eq!(Region::default().name, "tampere");
+}
+
+#[test]
+fn region_fields_override_preset() {
+    let settings = with_region("preset = \"turku\"\nzoom = 14.0\nfeed_id = \"oma\"").unwrap();
+    let turku = Region::preset("turku").unwrap();
+    assert_eq!(settings.region.zoom, 14.0);
+    assert_eq!(settings.region.feed_id, "oma");
+    // Ohittamattomat kentät tulevat valmiista alueesta
+    assert_eq!(settings.region.name, "turku");
+    assert_eq!(settings.region.router, Router::Waltti);
+    assert_eq!((settings.region.center_lat, settings.region.center_lon), (turku.center_lat, turku.center_lon));
+
+    // Ilman presetiä pohjana on oletusalue
+    let settings = with_region("router = \"hsl\"").unwrap();
+    assert_eq!(settings.region.name, "tampere");
+    assert_eq!(settings.region.router, Router::Hsl);
+    assert_eq!(settings.region.feed_id, "tampere");
+
+    let error = with_region("preset = \"tukholma\"").unwrap_err();
+    assert!(error.contains("tuntematon alue \"tukholma\""), "{}", error);
+}
+
+#[test]
+fn nationwide_region_subscribes_every_vehicle_feed() {
+    let settings = with_region("preset = \"finland\"").unwrap();
+    assert_eq!(settings.vehicles.feed_id, "");
+    assert_eq!(topics(&settings.vehicles, &None), ["/gtfsrt/vp/+/#"]);
+    assert!(topics(&settings.vehicles, &Some(vec!["3".to_string()]))[0].starts_with("/gtfsrt/vp/+/+/"));
+
+    // Alueen syöte periytyy ajoneuvoille
+    let settings = with_region("preset = \"oulu\"").unwrap();
+    assert_eq!(topics(&settings.vehicles, &None), ["/gtfsrt/vp/OULU/#"]);
+}
+
+fn region_env(preset: &str) -> Overrides {
+    let preset = preset.to_string();
+    Overrides::from_lookup(move |var| (var == "BUSSIVAHTI_REGION").then(|| preset.clone()))
+}
+
+#[test]
+fn environment_region_keeps_file_region_fields() {
+    let text = r#"
+version = 1
+[api]
+api_key = "k"
+[app]
+update_interval = 15
+[region]
+preset = "turku"
+zoom = 14.0
+[stops]
+"0802" = ["1"]
+"#;
+    let settings = Settings::parse_with(text, &region_env("oulu")).unwrap();
+    assert_eq!(settings.region.name, "oulu");
+    assert_eq!(settings.region.zoom, 14.0);
+    // Lyhyt tunnus saa ympäristön alueen syötteen
+    assert_eq!(settings.stops[0].id.as_str(), "OULU:0802");
+
+    // Tiedoston oma feed_id voittaa presetin
+    let own_feed = text.replace("zoom = 14.0", "feed_id = \"oma\"");
+    let settings = Settings::parse_with(&own_feed, &region_env("oulu")).unwrap();
+    assert_eq!(settings.stops[0].id.as_str(), "oma:0802");
+}
+
+#[test]
+fn check_resolves_stop_ids_with_environment_region() {
+    let text = r#"
+version = 1
+[api]
+api_key = "k"
+[app]
+update_interval = 15
+[stops]
+"0802" = ["1"]
+"OULU:0802" = ["2"]
+"#;
+    // Oletusalueella (tampere) tunnukset ovat eri pysäkkejä, Oulussa sama
+    let duplicate = |problems: Vec<schema::ConfigProblem>| problems.iter().any(|p| p.message.contains("on jo listassa"));
+    assert!(!duplicate(schema::check(text)));
+    assert!(duplicate(schema::check_provided(text, &region_env("oulu"))));
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use bussivahti_pro::models::{AlertSeverity, Stop};
+use bussivahti_pro::network::build_stop_data;
+use bussivahti_pro::settings::StopConfig;
+use chrono::{DateTime, Duration};
+use chrono_tz::Tz;
+use common::{noon, stop_json};
+use serde_json::{json, Value};
+
+fn alert(id: &str, severity: &str, start: Option<i64>, end: Option<i64>) -> Value {
+    json!({
+        "id": id,
+        "alertHeaderText": format!("Tiedote {}", id),
+        "alertSeverityLevel": severity,
+        "effectiveStartDate": start,
+        "effectiveEndDate": end,
+    })
+}
+
+fn minutes(m: i64) -> i64 {
+    (noon() + Duration::minutes(m)).timestamp()
+}
+
+// Pysäkin oma tiedote toistuu linjalla 3; linjatiedote koskee linjoja 3, 70 ja seuraamatonta 99
+fn stop_with_alerts() -> Stop {
+    let line_alert = alert("linja", "WARNING", None, None);
+    let mut stop = stop_json(Vec::new());
+    stop["alerts"] = json!([
+        alert("pysakki", "INFO", None, None),
+        alert("vanha", "SEVERE", Some(minutes(-120)), Some(minutes(-1))),
+    ]);
+    stop["routes"] = json!([
+        { "shortName": "3", "alerts": [alert("pysakki", "INFO", None, None), line_alert.clone()] },
+        { "shortName": "70", "alerts": [line_alert.clone(), alert("pian", "SEVERE", None, Some(minutes(10)))] },
+        { "shortName": "99", "alerts": [line_alert, alert("muu", "SEVERE", None, None)] },
+        { "shortName": "4", "alerts": [alert("tuleva", "WARNING", Some(minutes(60)), None)] },
+    ]);
+    serde_json::from_value(stop).unwrap()
+}
+
+fn config() -> StopConfig {
+    common::config(&["3", "70", "4"])
+}
+
+#[test]
+fn collects_alerts_once_per_id_and_drops_expired() {
+    let data = build_stop_data(stop_with_alerts(), &config(), noon());
+    let ids: Vec<&str> = data.alerts.iter().map(|a| a.id.as_str()).collect();
+    // Vanhentunut ja seuraamattoman linjan tiedote puuttuvat, toistot yhdistetty
+    assert_eq!(ids, ["pysakki", "linja", "pian", "tuleva"]);
+
+    let alert = |id: &str| data.alerts.iter().find(|a| a.id == id).unwrap();
+    // Koko pysäkin tiedote pysyy koko pysäkin tiedotteena
+    assert!(alert("pysakki").lines.is_empty());
+    assert_eq!(alert("linja").lines, ["3", "70"]);
+    assert_eq!(alert("pian").lines, ["70"]);
+}
+
+#[test]
+fn active_alerts_hide_ended_and_sort_by_severity() {
+    let data = build_stop_data(stop_with_alerts(), &config(), noon());
+
+    let active = |t: DateTime<Tz>| data.active_alerts(t).iter().map(|a| a.id.clone()).collect::<Vec<_>>();
+    assert_eq!(active(noon()), ["pian", "linja", "pysakki"]);
+    assert_eq!(data.active_alerts(noon())[0].severity, AlertSeverity::Severe);
+
+    // Piirtohetkellä päättynyt piilotetaan ilman uutta hakua, alkanut tulee näkyviin
+    assert_eq!(active(noon() + Duration::minutes(15)), ["linja", "pysakki"]);
+    assert_eq!(active(noon() + Duration::minutes(60)), ["linja", "tuleva", "pysakki"]);
+}
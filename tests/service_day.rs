@@ -0,0 +1,91 @@
+mod common;
+
+use bussivahti_pro::models::{service_day_start, FetchError, FetchErrorKind, GqlResponse, StopTime, TIMEZONE};
+use bussivahti_pro::network::build_stop_data;
+use chrono::{Duration, TimeZone};
+use common::{config, date, hms, stop, stoptime_json};
+use serde_json::json;
+
+fn stoptime(service_day: i64, seconds: i64) -> StopTime {
+    serde_json::from_value(stoptime_json(service_day, seconds, "3")).unwrap()
+}
+
+#[test]
+fn service_day_starts_at_local_midnight_on_normal_days() {
+    let start = service_day_start(date(2026, 6, 14));
+    let midnight = TIMEZONE.with_ymd_and_hms(2026, 6, 14, 0, 0, 0).unwrap();
+    assert_eq!(start, midnight.timestamp());
+}
+
+#[test]
+fn after_midnight_trip_lands_on_next_calendar_day() {
+    // 25:10 edellisen päivän aikataulussa = 01:10 seuraavana päivänä
+    let st = stoptime(service_day_start(date(2026, 6, 14)), hms(25, 10));
+    let expected = TIMEZONE.with_ymd_and_hms(2026, 6, 15, 1, 10, 0).unwrap();
+    assert_eq!(st.departure_time(), expected);
+}
+
+#[test]
+fn after_midnight_trip_counts_down_from_current_time() {
+    let stop = stop(vec![stoptime_json(service_day_start(date(2026, 6, 14)), hms(25, 10), "3")]);
+    let now = TIMEZONE.with_ymd_and_hms(2026, 6, 15, 1, 0, 0).unwrap();
+
+    let data = build_stop_data(stop, &config(&["ALL"]), now);
+
+    assert_eq!(data.departures.len(), 1);
+    assert_eq!(data.departures[0].predicted_time.format("%H:%M").to_string(), "01:10");
+    assert_eq!(data.departures[0].minutes_left(now), 10);
+    // Laskuri etenee ilman uutta hakua ja lähtenyt putoaa pois
+    assert_eq!(data.departures[0].minutes_left(now + Duration::minutes(4)), 6);
+    assert_eq!(data.upcoming(now + Duration::minutes(11)).count(), 0);
+}
+
+#[test]
+fn spring_forward_keeps_wall_clock_times() {
+    // 29.3.2026 kello siirtyy 03:00 -> 04:00
+    let day = date(2026, 3, 29);
+    let start = service_day_start(day);
+    assert_eq!(start, chrono::Utc.with_ymd_and_hms(2026, 3, 28, 21, 0, 0).unwrap().timestamp());
+
+    let st = stoptime(start, hms(8, 0));
+    let dep = st.departure_time();
+    assert_eq!(dep.format("%Y-%m-%d %H:%M %:z").to_string(), "2026-03-29 08:00 +03:00");
+}
+
+#[test]
+fn fall_back_keeps_wall_clock_times() {
+    // 25.10.2026 kello siirtyy 04:00 -> 03:00
+    let day = date(2026, 10, 25);
+    let start = service_day_start(day);
+    assert_eq!(start, chrono::Utc.with_ymd_and_hms(2026, 10, 24, 22, 0, 0).unwrap().timestamp());
+
+    let st = stoptime(start, hms(8, 0));
+    let dep = st.departure_time();
+    assert_eq!(dep.format("%Y-%m-%d %H:%M %:z").to_string(), "2026-10-25 08:00 +02:00");
+}
+
+#[test]
+fn departures_in_repeated_hour_stay_an_hour_apart() {
+    // Syksyllä 03:30 esiintyy kahdesti; kumpikaan ei saa romahtaa "0 min":ksi
+    let start = service_day_start(date(2026, 10, 25));
+    let first = stoptime(start, hms(2, 30)).departure_time();
+    let second = stoptime(start, hms(3, 30)).departure_time();
+    let now = chrono::Utc.with_ymd_and_hms(2026, 10, 25, 0, 0, 0).unwrap().with_timezone(&TIMEZONE);
+
+    assert_eq!(first.format("%H:%M").to_string(), "03:30");
+    assert_eq!(second.format("%H:%M").to_string(), "03:30");
+    assert_eq!(first.signed_duration_since(now).num_minutes(), 30);
+    assert_eq!(second.signed_duration_since(now).num_minutes(), 90);
+}
+
+#[test]
+fn missing_service_day_is_a_parse_error() {
+    let mut stoptime = stoptime_json(service_day_start(date(2026, 6, 14)), hms(12, 0), "3");
+    stoptime.as_object_mut().unwrap().remove("serviceDay");
+    let response = json!({ "data": { "s0": common::stop_json(vec![stoptime]) } });
+
+    // Ei hiljaista nollaa (1970), vaan näkyvä hakuvirhe
+    let error: FetchError = serde_json::from_value::<GqlResponse>(response).unwrap_err().into();
+    assert_eq!(error.kind, FetchErrorKind::Parse);
+    assert!(error.message.contains("serviceDay"), "{}", error.message);
+}
@@ -0,0 +1,141 @@
+use bussivahti_pro::schema::{self, Severity};
+use bussivahti_pro::settings::{Overrides, Settings};
+
+#[test]
+fn loads_sectioned_layout() {
+    let settings = Settings::parse(
+        r#"
+version = 1
+[api]
+api_key = "k"
+[app]
+update_interval = 15
+[stops]
+"0802" = ["1"]
+"FOLI:T24" = { lines = ["3"], per_line = 2 }
+"#,
+    )
+    .unwrap();
+    assert_eq!(settings.api_key, "k");
+    assert_eq!(settings.update_interval, 15);
+    // Pelkkä koodi saa alueen etuliitteen
+    assert!(settings.stop(&"tampere:0802".parse().unwrap()).is_some());
+    assert_eq!(settings.stops[1].per_line, Some(2));
+    assert!(settings.warnings.is_empty());
+}
+
+#[test]
+fn keeps_stop_order_in_both_forms() {
+    let listed = Settings::parse(
+        r#"
+[api]
+api_key = "k"
+[app]
+update_interval = 15
+[[stops]]
+id = "tampere:3"
+name = "Koti"
+walk_minutes = 4
+collapsed = true
+[[stops]]
+id = "tampere:1"
+lines = ["1"]
+hidden = true
+[[stops]]
+id = "tampere:2"
+"#,
+    )
+    .unwrap();
+    let ids: Vec<&str> = listed.stops.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, ["tampere:3", "tampere:1", "tampere:2"]);
+    assert_eq!(listed.stops[0].name.as_deref(), Some("Koti"));
+    // Kävelyaika toimii oletuksena hakuikkunan alkuna
+    assert_eq!(listed.stops[0].start_offset(), 4);
+    assert!(listed.stops[0].collapsed);
+    assert_eq!(listed.visible_stops().count(), 2);
+
+    let mapped = Settings::parse(
+        r#"
+[api]
+api_key = "k"
+[app]
+update_interval = 15
+[stops]
+"tampere:9" = ["1"]
+"tampere:0" = ["2"]
+"tampere:5" = { lines = ["3"] }
+"#,
+    )
+    .unwrap();
+    let ids: Vec<&str> = mapped.stops.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, ["tampere:9", "tampere:0", "tampere:5"]);
+}
+
+#[test]
+fn loads_old_flat_layout_with_warning() {
+    let settings = Settings::parse(
+        r#"
+api_key = "k"
+update_interval = 30
+fixture = "fixtures/demo.json"
+[stops]
+"tampere:0802" = ["1"]
+"#,
+    )
+    .unwrap();
+    assert_eq!(settings.api_key, "k");
+    assert_eq!(settings.fixture.as_deref(), Some("fixtures/demo.json"));
+    assert_eq!(settings.warnings.len(), 1);
+    assert_eq!(settings.warnings[0].severity, Severity::Warning);
+}
+
+#[test]
+fn reports_every_problem_with_line() {
+    let text = r#"version = 1
+[api]
+[app]
+update_interval = "30"
+[stops]
+"tampere:0802" = "1"
+[region]
+preset = "narnia"
+"#;
+    let problems = schema::check(text);
+    let found: Vec<(Option<usize>, &str)> = problems.iter().map(|p| (p.line, p.key.as_str())).collect();
+    assert_eq!(
+        found,
+        vec![
+            (Some(2), "api.api_key"),
+            (Some(4), "app.update_interval"),
+            (Some(6), "stops.\"tampere:0802\""),
+            (Some(8), "region.preset"),
+        ]
+    );
+    assert!(problems.iter().all(|p| p.is_error()));
+    assert!(Settings::parse(text).is_err());
+}
+
+#[test]
+fn environment_overrides_keys() {
+    let text = r#"
+[app]
+update_interval = 30
+[stops]
+"0802" = ["1"]
+"#;
+    // Avain puuttuu tiedostosta, mutta tulee ympäristöstä
+    assert!(Settings::parse(text).is_err());
+    let env = Overrides::from_lookup(|var| match var {
+        "BUSSIVAHTI_API_KEY" => Some("secret".to_string()),
+        "BUSSIVAHTI_REGION" => Some("turku".to_string()),
+        _ => None,
+    });
+    let settings = Settings::parse_with(text, &env).unwrap();
+    assert_eq!(settings.api_key, "secret");
+    assert_eq!(settings.region.name, "turku");
+    assert_eq!(settings.stops[0].id.as_str(), "FOLI:0802");
+
+    let bad = Overrides::from_lookup(|var| (var == "BUSSIVAHTI_UPDATE_INTERVAL").then(|| "soon".to_string()));
+    let problems = Settings::parse_with(text, &bad).unwrap_err();
+    assert!(problems.iter().any(|p| p.key == "BUSSIVAHTI_UPDATE_INTERVAL"));
+}
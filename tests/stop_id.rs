@@ -0,0 +1,38 @@
+use bussivahti_pro::models::{StopId, StopIdError};
+
+#[test]
+fn accepts_plain_gtfs_id() {
+    let id: StopId = "tampere:0802".parse().unwrap();
+    assert_eq!(id.feed(), "tampere");
+    assert_eq!(id.code(), "0802");
+    assert_eq!(id.to_string(), "tampere:0802");
+}
+
+#[test]
+fn accepts_geocoder_id_and_gid() {
+    let from_id: StopId = "GTFS:tampere:0802#0802".parse().unwrap();
+    let from_gid: StopId = "gtfstampere:stop:GTFS:tampere:0802#0802".parse().unwrap();
+    assert_eq!(from_id.as_str(), "tampere:0802");
+    assert_eq!(from_gid, from_id);
+}
+
+#[test]
+fn bare_code_needs_region_feed() {
+    assert_eq!(StopId::parse("0802", Some("tampere")).unwrap().as_str(), "tampere:0802");
+    assert_eq!(StopId::parse("0802", None), Err(StopIdError::MissingFeed("0802".to_string())));
+    // Täysi tunnus voittaa alueen syötteen
+    assert_eq!(StopId::parse("FOLI:T24", Some("tampere")).unwrap().as_str(), "FOLI:T24");
+}
+
+#[test]
+fn rejects_empty_and_malformed_ids() {
+    assert_eq!(StopId::parse("  ", Some("tampere")), Err(StopIdError::Empty));
+    assert!(StopId::parse("tampere:", None).is_err());
+    assert!(StopId::parse("tampere:08 02", None).is_err());
+}
+
+#[test]
+fn round_trips_through_serde() {
+    let id: StopId = serde_json::from_str("\"GTFS:tampere:0001#0001\"").unwrap();
+    assert_eq!(serde_json::to_string(&id).unwrap(), "\"tampere:0001\"");
+}
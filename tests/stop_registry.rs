@@ -0,0 +1,88 @@
+use bussivahti_pro::registry::StopRegistry;
+use bussivahti_pro::settings::{Overrides, Settings, StopConfig};
+use std::path::{Path, PathBuf};
+
+fn temp_settings(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("bussivahti-{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+// Kuten Settings::load, mutta ilman ympäristömuuttujia (BUSSIVAHTI_*)
+fn load(path: &Path) -> Settings {
+    let text = std::fs::read_to_string(path).unwrap();
+    let mut settings = Settings::parse_with(&text, &Overrides::default()).unwrap();
+    settings.path = Some(path.to_path_buf());
+    settings
+}
+
+const LISTED: &str = r#"# Oma asetustiedosto
+[api]
+api_key = "k"
+
+[app]
+update_interval = 30
+
+# Kotipysäkki
+[[stops]]
+id = "0802"          # lyhyt tunnus
+lines = ["1"]        # vain ratikka
+
+[[stops]]
+id = "tampere:0001"
+name = "Keskustori"
+
+[region]
+preset = "tampere"   # oletus
+"#;
+
+#[test]
+fn edits_are_written_back_with_comments() {
+    let path = temp_settings("listed", LISTED);
+    let settings = load(&path);
+    let registry = StopRegistry::new(&settings);
+
+    registry.add(StopConfig::new("tampere:0505".parse().unwrap(), vec!["ALL".to_string()])).unwrap();
+    registry.move_to(&"tampere:0505".parse().unwrap(), 0).unwrap();
+    registry.update(&"tampere:0802".parse().unwrap(), |s| s.lines = vec!["1".to_string(), "3".to_string()]).unwrap();
+    registry.remove(&"tampere:0001".parse().unwrap()).unwrap();
+    assert!(registry.add(StopConfig::new("tampere:0802".parse().unwrap(), Vec::new())).is_err());
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let reloaded = load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let ids: Vec<&str> = reloaded.stops.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, ["tampere:0505", "tampere:0802"]);
+    assert_eq!(reloaded.stops[1].lines, ["1", "3"]);
+    for comment in ["# Oma asetustiedosto", "# Kotipysäkki", "# lyhyt tunnus", "# vain ratikka", "# oletus"] {
+        assert!(text.contains(comment), "{} puuttuu:\n{}", comment, text);
+    }
+    assert!(!text.contains("Keskustori"));
+    // [region] pysyy pysäkkien jälkeen
+    assert!(text.find("[region]").unwrap() > text.rfind("[[stops]]").unwrap());
+}
+
+#[test]
+fn old_map_form_keeps_its_shape() {
+    let path = temp_settings("mapped", r#"[api]
+api_key = "k"
+[app]
+update_interval = 30
+[stops]
+"tampere:0802" = ["1"]   # ratikka
+"tampere:0001" = ["3"]
+"#);
+    let settings = load(&path);
+    let registry = StopRegistry::new(&settings);
+    registry.move_to(&"tampere:0001".parse().unwrap(), 0).unwrap();
+    registry.update(&"tampere:0001".parse().unwrap(), |s| s.walk_minutes = 5).unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let reloaded = load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(text.contains(r#""tampere:0802" = ["1"]   # ratikka"#), "{}", text);
+    assert_eq!(reloaded.stops[0].id.as_str(), "tampere:0001");
+    assert_eq!(reloaded.stops[0].walk_minutes, 5);
+}
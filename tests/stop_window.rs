@@ -0,0 +1,80 @@
+mod common;
+
+use bussivahti_pro::models::Stop;
+use bussivahti_pro::network::{build_stop_data, stop_request};
+use bussivahti_pro::settings::StopConfig;
+use common::{after_noon, noon};
+use serde_json::Value;
+
+// Linja 1 kahden minuutin välein koko tunnin, linja 70 kerran klo 12:30
+fn busy_stop() -> Stop {
+    let mut stoptimes: Vec<Value> = (0..30).map(|i| after_noon(1 + 2 * i, "1")).collect();
+    stoptimes.push(after_noon(30, "70"));
+    stoptimes.push(after_noon(31, "99"));
+    common::stop(stoptimes)
+}
+
+fn config() -> StopConfig {
+    common::config(&["1", "70"])
+}
+
+fn lines(stop: Stop, config: &StopConfig) -> Vec<(String, String)> {
+    build_stop_data(stop, config, noon())
+        .departures
+        .iter()
+        .map(|d| (d.line.clone(), d.predicted_time.format("%H:%M").to_string()))
+        .collect()
+}
+
+#[test]
+fn request_covers_window_and_sizes_patterns() {
+    let mut config = config();
+    config.lookahead_minutes = 45;
+    let request = stop_request(&config, noon());
+    assert_eq!(request.start_time, 0);
+    assert_eq!(request.time_range, 45 * 60);
+    assert_eq!(request.departures_per_pattern, 5);
+
+    // Kävelyaika siirtää haun alkua; per_line kasvattaa patternin lähtömäärää
+    config.walk_minutes = 4;
+    config.per_line = Some(8);
+    let request = stop_request(&config, noon());
+    assert_eq!(request.start_time, noon().timestamp() + 4 * 60);
+    assert_eq!(request.departures_per_pattern, 8);
+
+    // Oma alku ohittaa kävelyajan
+    config.start_offset_minutes = Some(0);
+    assert_eq!(stop_request(&config, noon()).start_time, 0);
+}
+
+#[test]
+fn window_drops_departures_outside_lookahead() {
+    let mut config = config();
+    config.walk_minutes = 2;
+    config.lookahead_minutes = 10;
+    config.max_departures = 100;
+    let times: Vec<String> = lines(busy_stop(), &config).into_iter().map(|(_, t)| t).collect();
+    // 12:02 – 12:12: linja 1 klo :03 ... :11
+    assert_eq!(times, ["12:03", "12:05", "12:07", "12:09", "12:11"]);
+}
+
+#[test]
+fn max_departures_alone_crowds_out_rare_line() {
+    let mut config = config();
+    config.max_departures = 4;
+    let shown = lines(busy_stop(), &config);
+    assert_eq!(shown.len(), 4);
+    assert!(shown.iter().all(|(line, _)| line == "1"));
+}
+
+#[test]
+fn per_line_keeps_rare_line_visible() {
+    let mut config = config();
+    config.max_departures = 4;
+    config.per_line = Some(2);
+    let shown = lines(busy_stop(), &config);
+    let expected = [("1", "12:01"), ("1", "12:03"), ("70", "12:30")];
+    assert_eq!(shown, expected.map(|(l, t)| (l.to_string(), t.to_string())));
+    // Suodattamaton linja 99 ei tule mukaan
+    assert!(!shown.iter().any(|(line, _)| line == "99"));
+}
@@ -0,0 +1,71 @@
+use bussivahti_pro::models::TIMEZONE;
+use bussivahti_pro::plan::{build_plan_query, decode_polyline, parse_clock, Place, PlanRequest, TimeConstraint, TransitMode};
+use chrono::TimeZone;
+
+fn request(time: TimeConstraint, modes: Vec<TransitMode>) -> PlanRequest {
+    PlanRequest {
+        from: Place::from_coordinates("61.4978,23.7603").unwrap(),
+        to: Place { name: "Hervannan \"kampus\"".to_string(), lat: 61.4495, lon: 23.8569 },
+        time,
+        modes,
+        count: 3,
+    }
+}
+
+#[test]
+fn decodes_google_polyline_sample() {
+    // Googlen dokumentaation esimerkki
+    let coords = decode_polyline("_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+    let pairs: Vec<(f64, f64)> = coords.iter().map(|c| (c.lat, c.lon)).collect();
+    assert_eq!(pairs, [(38.5, -120.2), (40.7, -120.95), (43.252, -126.453)]);
+}
+
+#[test]
+fn broken_polyline_returns_decoded_prefix() {
+    // Katkaistu kesken toisen pisteen
+    let coords = decode_polyline("_p~iF~ps|U_ulL");
+    assert_eq!(coords.len(), 1);
+    assert_eq!((coords[0].lat, coords[0].lon), (38.5, -120.2));
+
+    // Loputon jatkobitti ei saa ylivuotaa
+    assert!(decode_polyline(&"~".repeat(40)).is_empty());
+    let coords = decode_polyline(&format!("_p~iF~ps|U{}", "~".repeat(40)));
+    assert_eq!(coords.len(), 1);
+    assert!(decode_polyline("").is_empty());
+}
+
+#[test]
+fn parses_clock_in_finnish_time() {
+    let today = TIMEZONE.with_ymd_and_hms(2026, 6, 15, 7, 0, 0).unwrap();
+    assert_eq!(parse_clock(" 09:05 ", today), Some(TIMEZONE.with_ymd_and_hms(2026, 6, 15, 9, 5, 0).unwrap()));
+    assert!(parse_clock("9", today).is_none());
+    assert!(parse_clock("25:00", today).is_none());
+
+    // 29.3.2026 kello siirtyy 03:00 -> 04:00, joten 03:30 on olemassa vasta 04:30:nä
+    let spring = TIMEZONE.with_ymd_and_hms(2026, 3, 29, 1, 0, 0).unwrap();
+    let gap = parse_clock("03:30", spring).unwrap();
+    assert_eq!(gap.format("%H:%M %z").to_string(), "04:30 +0300");
+
+    // 25.10.2026 03:30 toistuu; valitaan kesäajan (ensimmäinen)
+    let autumn = TIMEZONE.with_ymd_and_hms(2026, 10, 25, 1, 0, 0).unwrap();
+    assert_eq!(parse_clock("03:30", autumn).unwrap().format("%z").to_string(), "+0300");
+}
+
+#[test]
+fn builds_plan_query_time_and_modes() {
+    let nine = TIMEZONE.with_ymd_and_hms(2026, 6, 15, 9, 0, 0).unwrap();
+
+    let now = build_plan_query(&request(TimeConstraint::Now, Vec::new()));
+    assert!(!now.contains("dateTime"));
+    assert!(!now.contains("modes:"));
+    assert!(now.contains("first: 3"));
+    assert!(now.contains("latitude: 61.4978, longitude: 23.7603"));
+    assert!(now.contains(r#"label: "Hervannan \"kampus\"""#));
+
+    let depart = build_plan_query(&request(TimeConstraint::DepartAt(nine), Vec::new()));
+    assert!(depart.contains(r#"dateTime: { earliestDeparture: "2026-06-15T09:00:00+03:00" }"#));
+
+    let arrive = build_plan_query(&request(TimeConstraint::ArriveBy(nine), vec![TransitMode::Bus, TransitMode::Tram]));
+    assert!(arrive.contains(r#"dateTime: { latestArrival: "2026-06-15T09:00:00+03:00" }"#));
+    assert!(arrive.contains("modes: { transit: { transit: [{ mode: BUS }, { mode: TRAM }] } }"));
+}
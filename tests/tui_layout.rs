@@ -0,0 +1,38 @@
+use bussivahti_pro::ui::grid_pages;
+use ratatui::layout::Rect;
+
+fn shown(pages: &[Vec<(usize, Rect)>]) -> Vec<usize> {
+    pages.iter().flatten().map(|(i, _)| *i).collect()
+}
+
+#[test]
+fn small_terminal_pages_every_stop() {
+    // 80x24 SSH-istunto, alapalkki vie yhden rivin
+    let area = Rect::new(0, 0, 80, 23);
+    let pages = grid_pages(area, &[false; 7]);
+    assert!(pages.len() > 1);
+    assert_eq!(shown(&pages), (0..7).collect::<Vec<_>>());
+    for (_, rect) in pages.iter().flatten() {
+        assert_eq!(rect.width, 80);
+        assert!(rect.bottom() <= area.bottom());
+        // Kutistettu, mutta otsikko ja vähintään kaksi lähtöä mahtuvat
+        assert!(rect.height >= 5);
+    }
+
+    // Vähän pysäkkejä: normaalikorkeus, ei sivutusta
+    let pages = grid_pages(area, &[false, true, false]);
+    assert_eq!(pages.len(), 1);
+    let heights: Vec<u16> = pages[0].iter().map(|(_, r)| r.height).collect();
+    assert_eq!(heights, [8, 3, 8]);
+}
+
+#[test]
+fn wide_terminal_uses_columns() {
+    let pages = grid_pages(Rect::new(0, 0, 220, 40), &[false; 6]);
+    assert_eq!(pages.len(), 1);
+    // 220 / 72 = kolme saraketta, kaksi riviä
+    let first_row: Vec<&Rect> = pages[0].iter().filter(|(_, r)| r.y == 0).map(|(_, r)| r).collect();
+    assert_eq!(first_row.len(), 3);
+    assert!(first_row.iter().all(|r| r.width >= 72 && r.height == 8));
+    assert_eq!(shown(&pages), (0..6).collect::<Vec<_>>());
+}
@@ -0,0 +1,126 @@
+use bussivahti_pro::registry::StopRegistry;
+use bussivahti_pro::settings::{StopConfig, VehicleFeedFormat, VehicleSettings};
+use bussivahti_pro::vehicles::{decode_gtfsrt, decode_hfp, topics, tracked_lines};
+use serde_json::json;
+
+fn gtfsrt(feed_id: &str) -> VehicleSettings {
+    VehicleSettings { feed_id: feed_id.to_string(), ..VehicleSettings::default() }
+}
+
+#[test]
+fn builds_topics_per_format() {
+    let lines = Some(vec!["3".to_string(), "70".to_string()]);
+    assert_eq!(
+        topics(&gtfsrt("tampere"), &lines),
+        [
+            "/gtfsrt/vp/tampere/+/+/+/+/+/+/+/+/+/+/+/+/+/+/3/#",
+            "/gtfsrt/vp/tampere/+/+/+/+/+/+/+/+/+/+/+/+/+/+/70/#",
+        ]
+    );
+    // "ALL" jollakin pysäkillä = koko syöte
+    assert_eq!(topics(&gtfsrt("tampere"), &None), ["/gtfsrt/vp/tampere/#"]);
+
+    let hfp = VehicleSettings { format: VehicleFeedFormat::Hfp, ..VehicleSettings::default() };
+    assert_eq!(topics(&hfp, &lines), ["/hfp/v2/journey/ongoing/vp/#"]);
+
+    let custom = VehicleSettings { topic: Some("/oma/#".to_string()), ..gtfsrt("tampere") };
+    assert_eq!(topics(&custom, &lines), ["/oma/#"]);
+}
+
+// --- protobuf käsin: (kenttä << 3 | tyyppi), pituus ja sisältö ---
+
+fn bytes(tag: u8, data: &[u8]) -> Vec<u8> {
+    assert!(data.len() < 128, "yksitavuinen pituus riittää testiin");
+    let mut out = vec![tag << 3 | 2, data.len() as u8];
+    out.extend_from_slice(data);
+    out
+}
+
+fn float(tag: u8, value: f32) -> Vec<u8> {
+    let mut out = vec![tag << 3 | 5];
+    out.extend_from_slice(&value.to_le_bytes());
+    out
+}
+
+// FeedEntity { id, vehicle: VehiclePosition { trip { route_id }, position, vehicle { id } } }
+fn entity(id: &str, route_id: &str, vehicle_id: &str, lat: f32, lon: f32, bearing: f32) -> Vec<u8> {
+    let trip = bytes(5, route_id.as_bytes());
+    let position = [float(1, lat), float(2, lon), float(3, bearing)].concat();
+    let descriptor = bytes(1, vehicle_id.as_bytes());
+    let vehicle = [bytes(1, &trip), bytes(2, &position), bytes(8, &descriptor)].concat();
+    bytes(2, &[bytes(1, id.as_bytes()), bytes(4, &vehicle)].concat())
+}
+
+#[test]
+fn decodes_gtfsrt_vehicle_positions() {
+    let feed = [entity("e1", "tampere:3", "bus-42", 61.5, 23.75, 90.0), entity("e2", "tampere:70", "bus-7", 61.4, 23.8, 180.0)].concat();
+
+    // HTTP-syöte: linja reitin id:stä
+    let vehicles = decode_gtfsrt(&feed, None);
+    assert_eq!(vehicles.len(), 2);
+    assert_eq!(vehicles[0].id, "bus-42");
+    assert_eq!(vehicles[0].line, "3");
+    assert_eq!((vehicles[0].lat, vehicles[0].lon), (61.5, 23.75));
+    assert_eq!(vehicles[0].heading, Some(90.0));
+    assert_eq!(vehicles[1].line, "70");
+    assert!(vehicles[0].mode.is_none());
+
+    // MQTT: linja ja kulkumuoto aiheesta
+    let topic = "/gtfsrt/vp/tampere/1/Nysse/TRAM/tampere:3/0/Hervanta/t1/0001/12:00/42/61;23/4/9/7/3/ffffff/";
+    let vehicles = decode_gtfsrt(&entity("e1", "tampere:3", "tram-1", 61.5, 23.75, 0.0), Some(topic));
+    assert_eq!(vehicles[0].line, "3");
+    assert!(vehicles[0].is_tram());
+
+    assert!(decode_gtfsrt(b"\xff\xff roskaa", None).is_empty());
+}
+
+#[test]
+fn decodes_hfp_message() {
+    let payload = json!({
+        "VP": { "desi": "550", "dir": "1", "oper": 22, "veh": 1234, "lat": 60.2, "long": 24.9, "hdg": 270, "spd": 8.5 }
+    })
+    .to_string();
+    let topic = "/hfp/v2/journey/ongoing/vp/bus/0022/01234/2550/1/Itäkeskus/12:00/1234567/0/60;24/19/09/22";
+
+    let vehicles = decode_hfp(payload.as_bytes(), topic);
+    assert_eq!(vehicles.len(), 1);
+    assert_eq!(vehicles[0].id, "22/1234");
+    assert_eq!(vehicles[0].line, "550");
+    assert_eq!(vehicles[0].mode.as_deref(), Some("bus"));
+    assert_eq!(vehicles[0].heading, Some(270.0));
+
+    // Ilman sijaintia ei piirretä mitään
+    let no_position = json!({ "VP": { "desi": "550", "lat": null, "long": null } }).to_string();
+    assert!(decode_hfp(no_position.as_bytes(), topic).is_empty());
+    assert!(decode_hfp(b"{}", topic).is_empty());
+}
+
+fn stop(id: &str, lines: &[&str]) -> StopConfig {
+    StopConfig::new(id.parse().unwrap(), lines.iter().map(|l| l.to_string()).collect())
+}
+
+#[test]
+fn tracked_lines_follow_visible_stops() {
+    let mut hidden = stop("tampere:0003", &["ALL"]);
+    hidden.hidden = true;
+    let stops = [stop("tampere:0001", &["70", "3"]), stop("tampere:0002", &["3", "1"]), hidden];
+    // Järjestetty ja yhdistetty, piilotettu "ALL" ei laajenna kaikkiin
+    assert_eq!(tracked_lines(&stops), Some(["1", "3", "70"].map(String::from).to_vec()));
+    assert_eq!(tracked_lines(&[stop("tampere:0001", &["ALL"])]), None);
+}
+
+#[tokio::test]
+async fn registry_changes_wake_vehicle_subscribers() {
+    let registry = StopRegistry::in_memory(vec![stop("tampere:0001", &["3"])]);
+    let mut changes = registry.subscribe();
+    assert!(!changes.has_changed().unwrap());
+
+    registry.add(stop("tampere:0002", &["70"])).unwrap();
+    assert!(changes.has_changed().unwrap());
+    changes.mark_unchanged();
+    assert_eq!(tracked_lines(&registry.snapshot()), Some(["3", "70"].map(String::from).to_vec()));
+
+    // Epäonnistunut muutos ei herätä
+    assert!(registry.remove(&"tampere:9999".parse().unwrap()).is_err());
+    assert!(!changes.has_changed().unwrap());
+}